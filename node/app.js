@@ -1,13 +1,25 @@
 import * as wasm from "webxr-rust";
 
-var xrApp = new wasm.XrApp();
-xrApp.init()
-    .then(res => {
-        if (res) {
-            console.log('init ok');
-            xrApp.start();
-        }
-        else {
-            console.log('init failed');
-        }
-    });
+var xrApp;
+try {
+    xrApp = new wasm.XrApp();
+}
+catch (err) {
+    console.log('WebGL2 unavailable: ' + err);
+}
+if (xrApp) {
+    // A triangle 2 meters wide in front of the origin.
+    xrApp.add_mesh(
+        [-1.4, -1.4, 0, 1, 0, 0, 1.4, -1.4, 0, 0, 1, 0, 0, 1.4, 0, 0, 0, 1],
+        [0, 1, 2]);
+    xrApp.init('immersive-vr', 'bounded-floor')
+        .then(res => {
+            if (res) {
+                console.log('init ok');
+                xrApp.start();
+            }
+            else {
+                console.log('init failed');
+            }
+        });
+}
@@ -0,0 +1,97 @@
+use crate::session_mode::SessionMode;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // `XRSession.enabledFeatures` isn't in web-sys yet, and is undefined on runtimes that
+    // don't implement it.
+    #[wasm_bindgen(extends = XrSession)]
+    type XrSessionFeatures;
+
+    #[wasm_bindgen(method, getter, js_name = enabledFeatures)]
+    fn enabled_features(this: &XrSessionFeatures) -> Option<js_sys::Array>;
+}
+
+// Summary of a session passed to the session start and end callbacks.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub mode: String,
+    pub reference_space: String,
+    pub enabled_features: Vec<String>,
+    // Input profiles of the input sources connected when the first frame was rendered.
+    pub input_profiles: Vec<String>,
+    // Frame timestamp of the first frame in milliseconds.
+    pub start_time: f64,
+    pub duration_ms: f64,
+    pub frame_count: u32,
+}
+
+fn strings(array: &js_sys::Array) -> Vec<String> {
+    array.iter().filter_map(|value| value.as_string()).collect()
+}
+
+// Features the runtime granted, or None when it doesn't report them.
+pub fn enabled_features(session: &XrSession) -> Option<Vec<String>> {
+    session
+        .unchecked_ref::<XrSessionFeatures>()
+        .enabled_features()
+        .as_ref()
+        .map(strings)
+}
+
+impl SessionInfo {
+    pub fn new(
+        session: &XrSession,
+        mode: SessionMode,
+        reference_space: XrReferenceSpaceType,
+    ) -> SessionInfo {
+        SessionInfo {
+            mode: String::from(mode.as_str()),
+            reference_space: JsValue::from(reference_space)
+                .as_string()
+                .unwrap_or_default(),
+            enabled_features: enabled_features(session).unwrap_or_default(),
+            ..SessionInfo::default()
+        }
+    }
+
+    // Returns true on the session's first frame.
+    pub fn frame(&mut self, time: f64, input_sources: &[XrInputSource]) -> bool {
+        let first = self.frame_count == 0;
+        if first {
+            self.start_time = time;
+            for source in input_sources {
+                for profile in strings(&source.profiles()) {
+                    if !self.input_profiles.contains(&profile) {
+                        self.input_profiles.push(profile);
+                    }
+                }
+            }
+        }
+        self.frame_count += 1;
+        self.duration_ms = time - self.start_time;
+        first
+    }
+}
+
+pub fn call_session_callback(callback: &Option<js_sys::Function>, info: &SessionInfo) {
+    let callback = if let Some(callback) = callback {
+        callback
+    } else {
+        return;
+    };
+    let info = match JsValue::from_serde(info) {
+        Ok(info) => info,
+        Err(err) => {
+            log!("session info error:{}", err);
+            return;
+        }
+    };
+    if let Err(err) = callback.call1(&JsValue::NULL, &info) {
+        log!("session callback error:{:?}", err);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::math::{self, Mat4};
+use js_sys::Promise;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // The WebXR Anchors Module isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFrameAnchors;
+
+    #[wasm_bindgen(method, catch, js_name = createAnchor)]
+    fn create_anchor(
+        this: &XrFrameAnchors,
+        pose: &XrRigidTransform,
+        space: &XrSpace,
+    ) -> Result<Promise, JsValue>;
+
+    // An XRAnchorSet, which is set-like.
+    #[wasm_bindgen(method, getter, js_name = trackedAnchors)]
+    fn tracked_anchors(this: &XrFrameAnchors) -> Option<js_sys::Set>;
+
+    type XrAnchor;
+
+    #[wasm_bindgen(method, getter, js_name = anchorSpace)]
+    fn anchor_space(this: &XrAnchor) -> XrSpace;
+
+    #[wasm_bindgen(method)]
+    fn delete(this: &XrAnchor);
+}
+
+fn rigid_transform(matrix: &Mat4) -> Result<XrRigidTransform, JsValue> {
+    let mut position = DomPointInit::new();
+    position
+        .x(matrix[12] as f64)
+        .y(matrix[13] as f64)
+        .z(matrix[14] as f64);
+    let [x, y, z, w] = math::quat_from_mat4(matrix);
+    let mut orientation = DomPointInit::new();
+    orientation.x(x as f64).y(y as f64).z(z as f64).w(w as f64);
+    XrRigidTransform::new_with_position_and_orientation(&position, &orientation)
+}
+
+// Anchors can only be created inside a frame, so requests wait here for the next one.
+struct PendingAnchor {
+    matrix: Mat4,
+    resolve: js_sys::Function,
+    reject: js_sys::Function,
+}
+
+pub struct AnchorCreation {
+    id: u32,
+    session: XrSession,
+    promise: Result<Promise, JsValue>,
+    resolve: js_sys::Function,
+    reject: js_sys::Function,
+}
+
+struct TrackedAnchor {
+    anchor: XrAnchor,
+    // None once the runtime stops tracking the anchor, which doesn't come back.
+    pose: Option<Mat4>,
+    tracked: bool,
+}
+
+#[derive(Default)]
+pub struct Anchors {
+    next_id: u32,
+    pending: Vec<PendingAnchor>,
+    anchors: HashMap<u32, TrackedAnchor>,
+}
+
+impl Anchors {
+    // Resolves with the anchor id once the anchor is created in a later frame.
+    pub fn request(&mut self, matrix: Mat4) -> Promise {
+        let pending = &mut self.pending;
+        Promise::new(&mut |resolve, reject| {
+            pending.push(PendingAnchor {
+                matrix,
+                resolve,
+                reject,
+            })
+        })
+    }
+
+    pub fn pose(&self, id: u32) -> Option<Mat4> {
+        self.anchors.get(&id).and_then(|anchor| anchor.pose)
+    }
+
+    pub fn is_tracked(&self, id: u32) -> bool {
+        self.anchors.get(&id).map(|anchor| anchor.tracked) == Some(true)
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        if let Some(anchor) = self.anchors.remove(&id) {
+            anchor.anchor.delete();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for (_, anchor) in self.anchors.drain() {
+            anchor.anchor.delete();
+        }
+        for pending in self.pending.drain(..) {
+            let _ = pending
+                .reject
+                .call1(&JsValue::NULL, &JsValue::from("the session ended"));
+        }
+    }
+
+    // Updates the poses of tracked anchors, and starts creating the pending ones. The
+    // creations are returned to be passed to `complete` once `self` is no longer borrowed.
+    pub fn update(&mut self, frame: &XrFrame, ref_space: &XrReferenceSpace) -> Vec<AnchorCreation> {
+        let frame = frame.unchecked_ref::<XrFrameAnchors>();
+        let tracked_anchors = frame.tracked_anchors();
+        for anchor in self.anchors.values_mut().filter(|anchor| anchor.tracked) {
+            anchor.tracked = tracked_anchors
+                .as_ref()
+                .map(|tracked_anchors| tracked_anchors.has(&anchor.anchor))
+                == Some(true);
+            anchor.pose = if anchor.tracked {
+                frame
+                    .get_pose(&anchor.anchor.anchor_space(), ref_space)
+                    .map(|pose| math::to_mat4(&pose.transform().matrix()))
+            } else {
+                None
+            };
+        }
+
+        let mut created = Vec::new();
+        for pending in self.pending.drain(..) {
+            let id = self.next_id;
+            self.next_id += 1;
+            let promise = rigid_transform(&pending.matrix)
+                .and_then(|transform| frame.create_anchor(&transform, ref_space));
+            created.push(AnchorCreation {
+                id,
+                session: frame.session(),
+                promise,
+                resolve: pending.resolve,
+                reject: pending.reject,
+            });
+        }
+        created
+    }
+
+    fn insert(&mut self, id: u32, anchor: XrAnchor) {
+        self.anchors.insert(
+            id,
+            TrackedAnchor {
+                anchor,
+                pose: None,
+                tracked: true,
+            },
+        );
+    }
+}
+
+// Waits for the anchor to be created, then starts tracking it and resolves the request with
+// its id. An anchor that arrives after its session ended is deleted and the request rejected,
+// as `Anchors::clear` does for requests still pending.
+pub fn complete(
+    anchors: Rc<RefCell<Anchors>>,
+    creation: AnchorCreation,
+    session: Rc<RefCell<Option<XrSession>>>,
+    running: Rc<RefCell<bool>>,
+) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let anchor = match creation.promise {
+            Ok(promise) => wasm_bindgen_futures::JsFuture::from(promise).await,
+            Err(err) => Err(err),
+        };
+        let current = *running.borrow() && session.borrow().as_ref() == Some(&creation.session);
+        let result = match anchor {
+            Ok(anchor) if !current => {
+                anchor.unchecked_into::<XrAnchor>().delete();
+                creation
+                    .reject
+                    .call1(&JsValue::NULL, &JsValue::from("the session ended"))
+            }
+            Ok(anchor) => {
+                anchors
+                    .borrow_mut()
+                    .insert(creation.id, anchor.unchecked_into());
+                creation
+                    .resolve
+                    .call1(&JsValue::NULL, &JsValue::from(creation.id))
+            }
+            Err(err) => creation.reject.call1(&JsValue::NULL, &err),
+        };
+        if let Err(err) = result {
+            log!("anchor promise error:{:?}", err);
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // `XRSession.environmentBlendMode` isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrSession)]
+    type XrSessionBlendMode;
+
+    #[wasm_bindgen(method, getter, js_name = environmentBlendMode)]
+    fn environment_blend_mode(this: &XrSessionBlendMode) -> Option<String>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AlphaMode {
+    Straight,
+    // Fragment shaders output color already multiplied by alpha.
+    Premultiplied,
+}
+
+impl AlphaMode {
+    // See-through ("alpha-blend") displays composite the layer as premultiplied alpha; opaque
+    // and additive displays don't care, so they keep straight alpha.
+    pub fn for_session(session: &XrSession) -> AlphaMode {
+        let blend_mode = session
+            .unchecked_ref::<XrSessionBlendMode>()
+            .environment_blend_mode();
+        AlphaMode::for_blend_mode(blend_mode.as_deref())
+    }
+
+    pub fn for_blend_mode(blend_mode: Option<&str>) -> AlphaMode {
+        match blend_mode {
+            Some("alpha-blend") => AlphaMode::Premultiplied,
+            _ => AlphaMode::Straight,
+        }
+    }
+
+    // Source and destination factors for `blend_func`.
+    pub fn blend_func(&self) -> (u32, u32) {
+        match self {
+            AlphaMode::Straight => (
+                WebGl2RenderingContext::SRC_ALPHA,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            ),
+            AlphaMode::Premultiplied => (
+                WebGl2RenderingContext::ONE,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA,
+            ),
+        }
+    }
+}
+
+// Factors `blend_func` accepts. SRC_ALPHA_SATURATE is only valid as a source factor.
+pub fn is_blend_factor(factor: u32, source: bool) -> bool {
+    match factor {
+        WebGl2RenderingContext::ZERO
+        | WebGl2RenderingContext::ONE
+        | WebGl2RenderingContext::SRC_COLOR
+        | WebGl2RenderingContext::ONE_MINUS_SRC_COLOR
+        | WebGl2RenderingContext::DST_COLOR
+        | WebGl2RenderingContext::ONE_MINUS_DST_COLOR
+        | WebGl2RenderingContext::SRC_ALPHA
+        | WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA
+        | WebGl2RenderingContext::DST_ALPHA
+        | WebGl2RenderingContext::ONE_MINUS_DST_ALPHA
+        | WebGl2RenderingContext::CONSTANT_COLOR
+        | WebGl2RenderingContext::ONE_MINUS_CONSTANT_COLOR
+        | WebGl2RenderingContext::CONSTANT_ALPHA
+        | WebGl2RenderingContext::ONE_MINUS_CONSTANT_ALPHA => true,
+        WebGl2RenderingContext::SRC_ALPHA_SATURATE => source,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_alpha_blend_sessions_are_premultiplied() {
+        assert_eq!(
+            AlphaMode::for_blend_mode(Some("alpha-blend")),
+            AlphaMode::Premultiplied
+        );
+        assert_eq!(
+            AlphaMode::for_blend_mode(Some("opaque")),
+            AlphaMode::Straight
+        );
+        assert_eq!(
+            AlphaMode::for_blend_mode(Some("additive")),
+            AlphaMode::Straight
+        );
+        assert_eq!(AlphaMode::for_blend_mode(None), AlphaMode::Straight);
+    }
+
+    #[test]
+    fn premultiplied_alpha_does_not_scale_the_source_again() {
+        assert_eq!(
+            AlphaMode::Straight.blend_func(),
+            (
+                WebGl2RenderingContext::SRC_ALPHA,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA
+            )
+        );
+        assert_eq!(
+            AlphaMode::Premultiplied.blend_func(),
+            (
+                WebGl2RenderingContext::ONE,
+                WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA
+            )
+        );
+    }
+
+    #[test]
+    fn src_alpha_saturate_is_only_a_source_factor() {
+        assert!(is_blend_factor(
+            WebGl2RenderingContext::SRC_ALPHA_SATURATE,
+            true
+        ));
+        assert!(!is_blend_factor(
+            WebGl2RenderingContext::SRC_ALPHA_SATURATE,
+            false
+        ));
+    }
+
+    #[test]
+    fn non_factors_are_rejected() {
+        assert!(is_blend_factor(WebGl2RenderingContext::ONE, false));
+        assert!(!is_blend_factor(WebGl2RenderingContext::DEPTH_TEST, true));
+        assert!(!is_blend_factor(WebGl2RenderingContext::FUNC_ADD, true));
+    }
+}
@@ -0,0 +1,46 @@
+use crate::GlContext;
+use serde::Serialize;
+use web_sys::*;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Capabilities {
+    pub max_texture_size: u32,
+    pub max_cube_map_texture_size: u32,
+    pub max_vertex_attribs: u32,
+    pub max_combined_texture_image_units: u32,
+    pub max_samples: u32,
+    pub extensions: Vec<String>,
+}
+
+fn get_u32<T: GlContext>(gl: &T, pname: u32) -> u32 {
+    gl.get_parameter(pname)
+        .ok()
+        .and_then(|value| value.as_f64())
+        .unwrap_or(0.) as u32
+}
+
+pub fn query<T: GlContext>(gl: &T) -> Capabilities {
+    let extensions = gl
+        .get_supported_extensions()
+        .map(|extensions| {
+            extensions
+                .iter()
+                .filter_map(|extension| extension.as_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Capabilities {
+        max_texture_size: get_u32(gl, WebGlRenderingContext::MAX_TEXTURE_SIZE),
+        max_cube_map_texture_size: get_u32(gl, WebGlRenderingContext::MAX_CUBE_MAP_TEXTURE_SIZE),
+        max_vertex_attribs: get_u32(gl, WebGlRenderingContext::MAX_VERTEX_ATTRIBS),
+        max_combined_texture_image_units: get_u32(
+            gl,
+            WebGlRenderingContext::MAX_COMBINED_TEXTURE_IMAGE_UNITS,
+        ),
+        // WebGL2 only, reported as 0 on WebGL1 contexts.
+        max_samples: get_u32(gl, WebGl2RenderingContext::MAX_SAMPLES),
+        extensions,
+    }
+}
@@ -0,0 +1,71 @@
+use crate::quality::LayerOptions;
+use crate::session_mode::SessionMode;
+use wasm_bindgen::prelude::*;
+use web_sys::XrReferenceSpaceType;
+
+/// Settings for `XrApp::with_config` and `XrApp::with_canvas_and_config`. Everything left unset
+/// keeps the default `init` and `XrApp::new` behavior, and arguments passed to `init` override
+/// the matching settings.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct XrAppConfig {
+    pub(crate) mode: Option<String>,
+    pub(crate) reference_space: Option<String>,
+    pub(crate) required_features: Option<js_sys::Array>,
+    pub(crate) optional_features: Option<js_sys::Array>,
+    pub(crate) clear_color: Option<[f32; 4]>,
+    pub(crate) framebuffer_scale: Option<f64>,
+    pub(crate) layer_options: Option<LayerOptions>,
+    pub(crate) mount_id: Option<String>,
+}
+
+#[wasm_bindgen]
+impl XrAppConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> XrAppConfig {
+        XrAppConfig::default()
+    }
+
+    // "immersive-vr", "immersive-ar" or "inline".
+    pub fn set_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        mode.parse::<SessionMode>()?;
+        self.mode = Some(String::from(mode));
+        Ok(())
+    }
+
+    pub fn set_reference_space(&mut self, reference_space: &str) -> Result<(), JsValue> {
+        if XrReferenceSpaceType::from_js_value(&JsValue::from(reference_space)).is_none() {
+            return Err(JsValue::from(format!(
+                "unknown reference space type: {}",
+                reference_space
+            )));
+        }
+        self.reference_space = Some(String::from(reference_space));
+        Ok(())
+    }
+
+    pub fn set_required_features(&mut self, features: js_sys::Array) {
+        self.required_features = Some(features);
+    }
+
+    pub fn set_optional_features(&mut self, features: js_sys::Array) {
+        self.optional_features = Some(features);
+    }
+
+    pub fn set_clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.clear_color = Some([r, g, b, a]);
+    }
+
+    pub fn set_framebuffer_scale(&mut self, scale: f64) {
+        self.framebuffer_scale = Some(scale);
+    }
+
+    pub fn set_layer_options(&mut self, options: LayerOptions) {
+        self.layer_options = Some(options);
+    }
+
+    // Id of the element to append the canvas to.
+    pub fn set_mount_id(&mut self, mount_id: String) {
+        self.mount_id = Some(mount_id);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::math::{transform_point, Mat4, Vec3, IDENTITY};
+use std::str::FromStr;
+
+// The axes a model was authored in. WebXR is right-handed with Y up and -Z forward.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CoordinateConvention {
+    YUp,
+    // Right-handed with Z up and the front facing -Y, as exported by Blender.
+    ZUp,
+    // Y up with +Z forward, as in Unity.
+    LeftHanded,
+}
+
+const CONVENTIONS: [CoordinateConvention; 3] = [
+    CoordinateConvention::YUp,
+    CoordinateConvention::ZUp,
+    CoordinateConvention::LeftHanded,
+];
+
+impl CoordinateConvention {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoordinateConvention::YUp => "y-up",
+            CoordinateConvention::ZUp => "z-up",
+            CoordinateConvention::LeftHanded => "left-handed",
+        }
+    }
+
+    // Column-major basis change into WebXR's convention. It only rotates or mirrors, so normals
+    // are transformed by it as well.
+    pub fn basis(&self) -> Mat4 {
+        match self {
+            CoordinateConvention::YUp => IDENTITY,
+            // (x, y, z) -> (x, z, -y)
+            CoordinateConvention::ZUp => [
+                1., 0., 0., 0., //
+                0., 0., -1., 0., //
+                0., 1., 0., 0., //
+                0., 0., 0., 1.,
+            ],
+            // (x, y, z) -> (x, y, -z)
+            CoordinateConvention::LeftHanded => [
+                1., 0., 0., 0., //
+                0., 1., 0., 0., //
+                0., 0., -1., 0., //
+                0., 0., 0., 1.,
+            ],
+        }
+    }
+
+    pub fn convert(&self, v: &Vec3) -> Vec3 {
+        transform_point(&self.basis(), v)
+    }
+
+    // Mirroring turns counter-clockwise triangles clockwise, so they need their winding reversed
+    // to stay front facing.
+    pub fn flips_winding(&self) -> bool {
+        *self == CoordinateConvention::LeftHanded
+    }
+}
+
+impl FromStr for CoordinateConvention {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        CONVENTIONS
+            .iter()
+            .find(|convention| convention.as_str() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown coordinate convention: {}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::dot;
+
+    #[test]
+    fn names_round_trip() {
+        for convention in CONVENTIONS.iter() {
+            assert_eq!(convention.as_str().parse(), Ok(*convention));
+        }
+        assert_eq!(
+            "x-up".parse::<CoordinateConvention>(),
+            Err(String::from("unknown coordinate convention: x-up"))
+        );
+    }
+
+    #[test]
+    fn z_up_turns_up_into_y_and_front_into_z() {
+        let convention = CoordinateConvention::ZUp;
+        assert_eq!(convention.convert(&[0., 0., 1.]), [0., 1., 0.]);
+        assert_eq!(convention.convert(&[0., -1., 0.]), [0., 0., 1.]);
+        assert_eq!(convention.convert(&[1., 0., 0.]), [1., 0., 0.]);
+        assert!(!convention.flips_winding());
+    }
+
+    #[test]
+    fn left_handed_forward_becomes_minus_z() {
+        let convention = CoordinateConvention::LeftHanded;
+        assert_eq!(convention.convert(&[0., 0., 1.]), [0., 0., -1.]);
+        assert_eq!(convention.convert(&[1., 2., 0.]), [1., 2., 0.]);
+        assert!(convention.flips_winding());
+    }
+
+    #[test]
+    fn only_mirroring_bases_flip_winding() {
+        for convention in CONVENTIONS.iter() {
+            let x = convention.convert(&[1., 0., 0.]);
+            let y = convention.convert(&[0., 1., 0.]);
+            let z = convention.convert(&[0., 0., 1.]);
+            let x_cross_y = [
+                x[1] * y[2] - x[2] * y[1],
+                x[2] * y[0] - x[0] * y[2],
+                x[0] * y[1] - x[1] * y[0],
+            ];
+            let mirrored = dot(&x_cross_y, &z) < 0.;
+            assert_eq!(mirrored, convention.flips_winding(), "{:?}", convention);
+        }
+    }
+}
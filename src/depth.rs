@@ -0,0 +1,267 @@
+use crate::features::Feature;
+use crate::math::{self, Mat4};
+use crate::memory::{self, GpuMemory};
+use crate::viewport::Viewport;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // The WebXR Depth Sensing Module isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFrameDepth;
+
+    // Throws when the session wasn't granted "depth-sensing", returns null for views the
+    // runtime has no depth for this frame.
+    #[wasm_bindgen(method, catch, js_name = getDepthInformation)]
+    fn get_depth_information(
+        this: &XrFrameDepth,
+        view: &XrView,
+    ) -> Result<Option<XrCpuDepthInformation>, JsValue>;
+
+    type XrCpuDepthInformation;
+
+    #[wasm_bindgen(method, getter)]
+    fn width(this: &XrCpuDepthInformation) -> i32;
+
+    #[wasm_bindgen(method, getter)]
+    fn height(this: &XrCpuDepthInformation) -> i32;
+
+    #[wasm_bindgen(method, getter, js_name = rawValueToMeters)]
+    fn raw_value_to_meters(this: &XrCpuDepthInformation) -> f32;
+
+    #[wasm_bindgen(method, getter, js_name = normDepthBufferFromNormView)]
+    fn norm_depth_buffer_from_norm_view(this: &XrCpuDepthInformation) -> XrRigidTransform;
+
+    #[wasm_bindgen(method, getter)]
+    fn data(this: &XrCpuDepthInformation) -> js_sys::ArrayBuffer;
+}
+
+// True when "depth-sensing" is among the requested features.
+pub fn is_requested(features: &[String]) -> bool {
+    features
+        .iter()
+        .any(|feature| feature == Feature::DepthSensing.as_str())
+}
+
+// Browsers reject "depth-sensing" without a depthSensing dictionary. Depth is read on the CPU
+// as 16-bit values, the format every implementation supports.
+pub fn request_depth_sensing(session_init: &XrSessionInit) -> Result<(), JsValue> {
+    let options = js_sys::Object::new();
+    let usage: js_sys::Array = std::iter::once(JsValue::from("cpu-optimized")).collect();
+    let format: js_sys::Array = std::iter::once(JsValue::from("luminance-alpha")).collect();
+    js_sys::Reflect::set(&options, &JsValue::from("usagePreference"), &usage)?;
+    js_sys::Reflect::set(&options, &JsValue::from("dataFormatPreference"), &format)?;
+    js_sys::Reflect::set(session_init, &JsValue::from("depthSensing"), &options)?;
+    Ok(())
+}
+
+// Per-view values for the main fragment shader's depth test.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthView {
+    // normDepthBufferFromNormView, mapping normalized view coordinates to depth texture ones.
+    pub uv_transform: Mat4,
+    pub raw_value_to_meters: f32,
+}
+
+impl DepthView {
+    // depthUvTransform: maps a fragment's window coordinates in `viewport` to depth texture
+    // coordinates. Normalized view coordinates have a top-left origin, and a mirrored view's
+    // fragments land on the opposite side, so its x runs from the right edge.
+    pub fn window_to_uv(&self, viewport: &Viewport, mirror: bool) -> Mat4 {
+        let (x, y) = (viewport.x as f32, viewport.y as f32);
+        let (width, height) = (viewport.width as f32, viewport.height as f32);
+        let mut norm_view = math::IDENTITY;
+        if mirror {
+            norm_view[0] = -1. / width;
+            norm_view[12] = (x + width) / width;
+        } else {
+            norm_view[0] = 1. / width;
+            norm_view[12] = -x / width;
+        }
+        norm_view[5] = -1. / height;
+        norm_view[13] = 1. + y / height;
+        math::multiply(&self.uv_transform, &norm_view)
+    }
+
+    // depthTexelWeights: the depth in meters is the dot product of these with a sampled
+    // luminance-alpha texel. Luminance holds the low byte and alpha the high byte of each raw
+    // value, each sampled as byte / 255.
+    pub fn texel_weights(&self) -> [f32; 2] {
+        [
+            255. * self.raw_value_to_meters,
+            65280. * self.raw_value_to_meters,
+        ]
+    }
+}
+
+// One depth texture per view, refilled from the CPU depth buffer every frame.
+#[derive(Default)]
+pub struct DepthOcclusion {
+    textures: Vec<WebGlTexture>,
+}
+
+impl DepthOcclusion {
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        for texture in &self.textures {
+            gpu_memory.delete_texture(gl, texture);
+        }
+    }
+
+    // Uploads the depth of view `index` and binds it to texture unit 0. None when the frame has
+    // no depth for the view, in which case nothing is bound and occlusion should be skipped
+    // for it rather than comparing against stale or empty depth.
+    pub fn upload(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        frame: &XrFrame,
+        view: &XrView,
+        index: usize,
+        gpu_memory: &mut GpuMemory,
+    ) -> Option<DepthView> {
+        let depth = frame
+            .unchecked_ref::<XrFrameDepth>()
+            .get_depth_information(view)
+            .ok()??;
+        while self.textures.len() <= index {
+            let texture = gl.create_texture()?;
+            gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+            for (parameter, value) in [
+                (
+                    WebGl2RenderingContext::TEXTURE_MIN_FILTER,
+                    WebGl2RenderingContext::LINEAR,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_MAG_FILTER,
+                    WebGl2RenderingContext::LINEAR,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_WRAP_S,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE,
+                ),
+                (
+                    WebGl2RenderingContext::TEXTURE_WRAP_T,
+                    WebGl2RenderingContext::CLAMP_TO_EDGE,
+                ),
+            ]
+            .iter()
+            {
+                gl.tex_parameteri(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    *parameter,
+                    *value as i32,
+                );
+            }
+            self.textures.push(texture);
+        }
+
+        gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        gl.bind_texture(
+            WebGl2RenderingContext::TEXTURE_2D,
+            Some(&self.textures[index]),
+        );
+        // Rows are 2 bytes per texel, so odd widths aren't 4-byte aligned.
+        gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 2);
+        let data = js_sys::Uint8Array::new(&depth.data());
+        let result = gl
+            .tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+                WebGl2RenderingContext::TEXTURE_2D,
+                0,
+                WebGl2RenderingContext::LUMINANCE_ALPHA as i32,
+                depth.width(),
+                depth.height(),
+                0,
+                WebGl2RenderingContext::LUMINANCE_ALPHA,
+                WebGl2RenderingContext::UNSIGNED_BYTE,
+                Some(&data),
+            );
+        gl.pixel_storei(WebGl2RenderingContext::UNPACK_ALIGNMENT, 4);
+        if let Err(err) = result {
+            log!("depth upload error:{:?}", err);
+            return None;
+        }
+        gpu_memory.texture_data(
+            &self.textures[index],
+            memory::texture_bytes(
+                depth.width(),
+                depth.height(),
+                1,
+                WebGl2RenderingContext::LUMINANCE_ALPHA,
+            ),
+        );
+
+        Some(DepthView {
+            uv_transform: math::to_mat4(&depth.norm_depth_buffer_from_norm_view().matrix()),
+            raw_value_to_meters: depth.raw_value_to_meters(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIEWPORT: Viewport = Viewport {
+        x: 100,
+        y: 0,
+        width: 200,
+        height: 100,
+    };
+
+    const DEPTH_VIEW: DepthView = DepthView {
+        uv_transform: math::IDENTITY,
+        raw_value_to_meters: 0.001,
+    };
+
+    fn uv(window_to_uv: &Mat4, x: f32, y: f32) -> [f32; 2] {
+        let [u, v, _] = math::transform_point(window_to_uv, &[x, y, 0.]);
+        [u, v]
+    }
+
+    #[test]
+    fn depth_sensing_is_detected_among_features() {
+        assert!(is_requested(&[
+            String::from("hit-test"),
+            String::from("depth-sensing")
+        ]));
+        assert!(!is_requested(&[String::from("hit-test")]));
+    }
+
+    #[test]
+    fn window_corners_map_to_normalized_view_corners() {
+        let window_to_uv = DEPTH_VIEW.window_to_uv(&VIEWPORT, false);
+        assert_eq!(uv(&window_to_uv, 100., 100.), [0., 0.]);
+        assert_eq!(uv(&window_to_uv, 300., 0.), [1., 1.]);
+        assert_eq!(uv(&window_to_uv, 200., 50.), [0.5, 0.5]);
+    }
+
+    #[test]
+    fn mirrored_views_flip_x() {
+        let window_to_uv = DEPTH_VIEW.window_to_uv(&VIEWPORT, true);
+        assert_eq!(uv(&window_to_uv, 300., 100.), [0., 0.]);
+        assert_eq!(uv(&window_to_uv, 150., 50.), [0.75, 0.5]);
+    }
+
+    #[test]
+    fn runtime_transform_applies_after_normalizing() {
+        // A depth buffer rotated a quarter turn, as on a portrait phone: u = 1 - y, v = x.
+        let rotated = DepthView {
+            uv_transform: [
+                0., 1., 0., 0., -1., 0., 0., 0., 0., 0., 1., 0., 1., 0., 0., 1.,
+            ],
+            ..DEPTH_VIEW
+        };
+        let window_to_uv = rotated.window_to_uv(&VIEWPORT, false);
+        assert_eq!(uv(&window_to_uv, 100., 100.), [1., 0.]);
+        assert_eq!(uv(&window_to_uv, 300., 100.), [1., 1.]);
+    }
+
+    #[test]
+    fn texel_weights_decode_little_endian_raw_values() {
+        // 0x0412 = 1042 raw units at 1 mm each, sampled as byte / 255.
+        let [low, high] = DEPTH_VIEW.texel_weights();
+        let meters = low * (0x12 as f32 / 255.) + high * (0x04 as f32 / 255.);
+        assert!((meters - 1.042).abs() < 1e-5);
+    }
+}
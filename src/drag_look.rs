@@ -0,0 +1,69 @@
+use web_sys::*;
+
+// Radians of rotation per CSS pixel dragged.
+const SENSITIVITY: f32 = 0.005;
+// Keeps the view from flipping over the poles.
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 * 0.99;
+
+// Look direction for inline sessions, turned by dragging on the canvas with a mouse, pen or
+// finger.
+#[derive(Default)]
+pub struct DragLook {
+    yaw: f32,
+    pitch: f32,
+    last: Option<(i32, i32)>,
+}
+
+impl DragLook {
+    pub fn press(&mut self, x: i32, y: i32) {
+        self.last = Some((x, y));
+    }
+
+    pub fn release(&mut self) {
+        self.last = None;
+    }
+
+    // Dragging right or down turns the view right or down.
+    pub fn drag(&mut self, x: i32, y: i32) {
+        let (last_x, last_y) = match self.last.replace((x, y)) {
+            Some(last) => last,
+            None => return,
+        };
+        self.yaw -= (x - last_x) as f32 * SENSITIVITY;
+        self.pitch = (self.pitch - (y - last_y) as f32 * SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // Quaternion (x, y, z, w) of the look rotation, yaw around Y then pitch around X.
+    fn orientation(&self) -> [f32; 4] {
+        let (sin_yaw, cos_yaw) = (self.yaw / 2.).sin_cos();
+        let (sin_pitch, cos_pitch) = (self.pitch / 2.).sin_cos();
+        [
+            cos_yaw * sin_pitch,
+            sin_yaw * cos_pitch,
+            -sin_yaw * sin_pitch,
+            cos_yaw * cos_pitch,
+        ]
+    }
+
+    // `ref_space` offset so that the viewer looks along the dragged direction. Poses in an
+    // offset space are relative to the offset, so the offset is the inverse of the look.
+    pub fn apply(&self, ref_space: &XrReferenceSpace) -> XrReferenceSpace {
+        if self.yaw == 0. && self.pitch == 0. {
+            return ref_space.clone();
+        }
+        let [x, y, z, w] = self.orientation();
+        let mut orientation = DomPointInit::new();
+        orientation
+            .x(-x as f64)
+            .y(-y as f64)
+            .z(-z as f64)
+            .w(w as f64);
+        match XrRigidTransform::new_with_position_and_orientation(
+            &DomPointInit::new(),
+            &orientation,
+        ) {
+            Ok(offset) => ref_space.get_offset_reference_space(&offset),
+            Err(_) => ref_space.clone(),
+        }
+    }
+}
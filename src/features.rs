@@ -0,0 +1,129 @@
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    Viewer,
+    Local,
+    LocalFloor,
+    BoundedFloor,
+    Unbounded,
+    Anchors,
+    HitTest,
+    HandTracking,
+    DomOverlay,
+    Layers,
+    DepthSensing,
+    LightEstimation,
+    PlaneDetection,
+    MeshDetection,
+    SecondaryViews,
+    CameraAccess,
+}
+
+const FEATURES: [Feature; 16] = [
+    Feature::Viewer,
+    Feature::Local,
+    Feature::LocalFloor,
+    Feature::BoundedFloor,
+    Feature::Unbounded,
+    Feature::Anchors,
+    Feature::HitTest,
+    Feature::HandTracking,
+    Feature::DomOverlay,
+    Feature::Layers,
+    Feature::DepthSensing,
+    Feature::LightEstimation,
+    Feature::PlaneDetection,
+    Feature::MeshDetection,
+    Feature::SecondaryViews,
+    Feature::CameraAccess,
+];
+
+impl Feature {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Feature::Viewer => "viewer",
+            Feature::Local => "local",
+            Feature::LocalFloor => "local-floor",
+            Feature::BoundedFloor => "bounded-floor",
+            Feature::Unbounded => "unbounded",
+            Feature::Anchors => "anchors",
+            Feature::HitTest => "hit-test",
+            Feature::HandTracking => "hand-tracking",
+            Feature::DomOverlay => "dom-overlay",
+            Feature::Layers => "layers",
+            Feature::DepthSensing => "depth-sensing",
+            Feature::LightEstimation => "light-estimation",
+            Feature::PlaneDetection => "plane-detection",
+            Feature::MeshDetection => "mesh-detection",
+            Feature::SecondaryViews => "secondary-views",
+            Feature::CameraAccess => "camera-access",
+        }
+    }
+}
+
+impl FromStr for Feature {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        FEATURES
+            .iter()
+            .find(|feature| feature.as_str() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown feature: {}", name))
+    }
+}
+
+pub fn is_known_feature(name: &str) -> bool {
+    name.parse::<Feature>().is_ok()
+}
+
+// Feature names from a JS array. Unknown names are kept, since runtimes may support features
+// newer than this list, but are logged as likely typos.
+pub fn feature_names(features: Option<&js_sys::Array>) -> Result<Vec<String>, String> {
+    let mut names = Vec::new();
+    for value in features.iter().flat_map(|features| features.iter()) {
+        let name = value
+            .as_string()
+            .ok_or_else(|| format!("feature names must be strings: {:?}", value))?;
+        if !is_known_feature(&name) {
+            log!("unknown feature \"{}\", check for typos", name);
+        }
+        names.push(name);
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_feature_parses_back_from_its_name() {
+        for feature in FEATURES.iter() {
+            assert_eq!(feature.as_str().parse::<Feature>(), Ok(*feature));
+        }
+    }
+
+    #[test]
+    fn known_features_are_exact_names() {
+        assert!(is_known_feature("hand-tracking"));
+        assert!(is_known_feature("local-floor"));
+        assert!(!is_known_feature("hand_tracking"));
+        assert!(!is_known_feature("Local-Floor"));
+        assert!(!is_known_feature(""));
+    }
+
+    #[test]
+    fn unknown_feature_error_names_it() {
+        assert_eq!(
+            "hit_test".parse::<Feature>(),
+            Err(String::from("unknown feature: hit_test"))
+        );
+    }
+
+    #[test]
+    fn no_array_means_no_features() {
+        assert_eq!(feature_names(None), Ok(Vec::new()));
+    }
+}
@@ -0,0 +1,47 @@
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // `XRSession.frameRate`, `supportedFrameRates` and `updateTargetFrameRate` aren't in web-sys
+    // yet, and are undefined on runtimes without frame rate control.
+    #[wasm_bindgen(extends = XrSession)]
+    type XrSessionFrameRate;
+
+    #[wasm_bindgen(method, getter, js_name = frameRate)]
+    fn frame_rate(this: &XrSessionFrameRate) -> Option<f32>;
+
+    #[wasm_bindgen(method, getter, js_name = supportedFrameRates)]
+    fn supported_frame_rates(this: &XrSessionFrameRate) -> Option<js_sys::Float32Array>;
+
+    #[wasm_bindgen(method, catch, js_name = updateTargetFrameRate)]
+    fn update_target_frame_rate(this: &XrSessionFrameRate, rate: f32) -> Result<Promise, JsValue>;
+}
+
+pub fn frame_rate(session: &XrSession) -> Option<f32> {
+    session.unchecked_ref::<XrSessionFrameRate>().frame_rate()
+}
+
+pub fn supported_frame_rates(session: &XrSession) -> Option<Vec<f32>> {
+    session
+        .unchecked_ref::<XrSessionFrameRate>()
+        .supported_frame_rates()
+        .map(|rates| rates.to_vec())
+}
+
+// Rejects when the runtime has no frame rate control or `hz` isn't one of the supported rates.
+pub fn request_frame_rate(session: &XrSession, hz: f32) -> Promise {
+    match supported_frame_rates(session) {
+        None => Promise::reject(&JsValue::from("frame rate control is not supported")),
+        Some(rates) if !rates.contains(&hz) => Promise::reject(&JsValue::from(format!(
+            "unsupported frame rate {}, supported: {:?}",
+            hz, rates
+        ))),
+        Some(_) => session
+            .unchecked_ref::<XrSessionFrameRate>()
+            .update_target_frame_rate(hz)
+            .unwrap_or_else(|err| Promise::reject(&err)),
+    }
+}
@@ -0,0 +1,204 @@
+use crate::math::Vec2;
+
+const EPSILON: f32 = 1e-6;
+
+fn cross(o: &Vec2, a: &Vec2, b: &Vec2) -> f32 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn signed_area(points: &[Vec2]) -> f32 {
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let (a, b) = (&points[i], &points[(i + 1) % n]);
+            a[0] * b[1] - b[0] * a[1]
+        })
+        .sum::<f32>()
+        / 2.
+}
+
+// Proper crossings only; segments that just touch at an endpoint don't count.
+fn segments_cross(a: &Vec2, b: &Vec2, c: &Vec2, d: &Vec2) -> bool {
+    let d1 = cross(a, b, c);
+    let d2 = cross(a, b, d);
+    let d3 = cross(c, d, a);
+    let d4 = cross(c, d, b);
+    ((d1 > EPSILON && d2 < -EPSILON) || (d1 < -EPSILON && d2 > EPSILON))
+        && ((d3 > EPSILON && d4 < -EPSILON) || (d3 < -EPSILON && d4 > EPSILON))
+}
+
+fn self_intersects(points: &[Vec2]) -> bool {
+    let n = points.len();
+    for i in 0..n {
+        for j in i + 2..n {
+            // The first and last edges share a vertex.
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            if segments_cross(
+                &points[i],
+                &points[(i + 1) % n],
+                &points[j],
+                &points[(j + 1) % n],
+            ) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+// Inclusive of the edges, so vertices touching an ear keep it from being clipped.
+fn in_triangle(p: &Vec2, a: &Vec2, b: &Vec2, c: &Vec2) -> bool {
+    cross(a, b, p) >= -EPSILON && cross(b, c, p) >= -EPSILON && cross(c, a, p) >= -EPSILON
+}
+
+// Ear-clipping triangulation of a simple polygon, convex or concave, given in either winding.
+// Returns indices into `points`, three per triangle, wound counter-clockwise. Collinear
+// vertices produce no triangles. Fails on fewer than 3 points, zero area or self-intersection.
+pub fn triangulate(points: &[Vec2]) -> Result<Vec<u16>, String> {
+    if points.len() < 3 {
+        return Err(String::from("polygon needs at least 3 points"));
+    }
+    if points.len() > u16::MAX as usize {
+        return Err(String::from("polygon has too many points for u16 indices"));
+    }
+    let area = signed_area(points);
+    if area.abs() < EPSILON {
+        return Err(String::from("polygon is degenerate"));
+    }
+    if self_intersects(points) {
+        return Err(String::from("polygon is self-intersecting"));
+    }
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    if area < 0. {
+        remaining.reverse();
+    }
+    let mut indices = Vec::with_capacity((points.len() - 2) * 3);
+    let mut i = 0;
+    let mut since_last_ear = 0;
+    while remaining.len() > 2 {
+        if since_last_ear > remaining.len() {
+            return Err(String::from("unable to triangulate polygon"));
+        }
+        let n = remaining.len();
+        let (prev, curr, next) = (
+            remaining[(i + n - 1) % n],
+            remaining[i % n],
+            remaining[(i + 1) % n],
+        );
+        let (a, b, c) = (&points[prev], &points[curr], &points[next]);
+        let turn = cross(a, b, c);
+        if turn.abs() < EPSILON {
+            remaining.remove(i % n);
+            since_last_ear = 0;
+            continue;
+        }
+        let is_ear = turn > 0.
+            && remaining
+                .iter()
+                .filter(|&&other| other != prev && other != curr && other != next)
+                .all(|&other| !in_triangle(&points[other], a, b, c));
+        if is_ear {
+            indices.extend_from_slice(&[prev as u16, curr as u16, next as u16]);
+            remaining.remove(i % n);
+            since_last_ear = 0;
+        } else {
+            i = (i + 1) % n;
+            since_last_ear += 1;
+        }
+    }
+    Ok(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn triangles(points: &[Vec2], indices: &[u16]) -> Vec<[Vec2; 3]> {
+        indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                [
+                    points[triangle[0] as usize],
+                    points[triangle[1] as usize],
+                    points[triangle[2] as usize],
+                ]
+            })
+            .collect()
+    }
+
+    // Every triangle is wound counter-clockwise and together they cover the polygon's area.
+    fn assert_fills(points: &[Vec2], indices: &[u16]) {
+        let triangles = triangles(points, indices);
+        assert!(triangles.iter().all(|triangle| signed_area(triangle) > 0.));
+        let covered: f32 = triangles.iter().map(|triangle| signed_area(triangle)).sum();
+        assert!((covered - signed_area(points).abs()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn convex_polygon_becomes_a_fan_of_n_minus_2_triangles() {
+        let hexagon: Vec<Vec2> = (0..6)
+            .map(|i| {
+                let angle = i as f32 * std::f32::consts::PI / 3.;
+                [angle.cos(), angle.sin()]
+            })
+            .collect();
+        let indices = triangulate(&hexagon).unwrap();
+        assert_eq!(indices.len(), 4 * 3);
+        assert_fills(&hexagon, &indices);
+    }
+
+    #[test]
+    fn concave_polygon_keeps_triangles_inside() {
+        // An L shape, reflex corner at (1, 1).
+        let l_shape = [[0., 0.], [2., 0.], [2., 1.], [1., 1.], [1., 2.], [0., 2.]];
+        let indices = triangulate(&l_shape).unwrap();
+        assert_eq!(indices.len(), 4 * 3);
+        assert_fills(&l_shape, &indices);
+        // No triangle spans the notch.
+        for triangle in triangles(&l_shape, &indices) {
+            let center = [
+                (triangle[0][0] + triangle[1][0] + triangle[2][0]) / 3.,
+                (triangle[0][1] + triangle[1][1] + triangle[2][1]) / 3.,
+            ];
+            assert!(!(center[0] > 1. && center[1] > 1.));
+        }
+    }
+
+    #[test]
+    fn collinear_vertices_add_no_degenerate_triangles() {
+        // A square with an extra vertex in the middle of its bottom edge.
+        let square = [[0., 0.], [1., 0.], [2., 0.], [2., 2.], [0., 2.]];
+        let indices = triangulate(&square).unwrap();
+        assert!(indices.len() <= 3 * 3);
+        // assert_fills rejects zero-area triangles.
+        assert_fills(&square, &indices);
+    }
+
+    #[test]
+    fn clockwise_input_is_wound_counter_clockwise() {
+        let clockwise = [[0., 0.], [0., 1.], [1., 1.], [1., 0.]];
+        let indices = triangulate(&clockwise).unwrap();
+        assert_eq!(indices.len(), 2 * 3);
+        assert_fills(&clockwise, &indices);
+    }
+
+    #[test]
+    fn fewer_than_three_points_are_rejected() {
+        assert!(triangulate(&[]).is_err());
+        assert!(triangulate(&[[0., 0.], [1., 0.]]).is_err());
+    }
+
+    #[test]
+    fn all_collinear_points_are_rejected() {
+        assert!(triangulate(&[[0., 0.], [1., 1.], [2., 2.], [3., 3.]]).is_err());
+    }
+
+    #[test]
+    fn self_intersecting_polygon_is_rejected() {
+        let bowtie = [[0., 0.], [1., 1.], [1., 0.], [0., 1.]];
+        assert!(triangulate(&bowtie).is_err());
+    }
+}
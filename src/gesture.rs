@@ -0,0 +1,176 @@
+use crate::math::{self, Mat4, Vec3};
+
+// Alternating swings above the threshold needed to count as a nod or shake.
+const SWINGS: u32 = 3;
+// A swing more than this long after the previous one starts a new gesture.
+const SWING_WINDOW_MS: f64 = 600.;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeadGesture {
+    Nod,
+    Shake,
+}
+
+impl HeadGesture {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HeadGesture::Nod => "nod",
+            HeadGesture::Shake => "shake",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Oscillation {
+    sign: f32,
+    swings: u32,
+    last_swing: f64,
+}
+
+impl Oscillation {
+    fn update(&mut self, rate: f32, threshold: f32, time: f64) -> bool {
+        if time - self.last_swing > SWING_WINDOW_MS {
+            *self = Oscillation::default();
+        }
+        if rate.abs() < threshold {
+            return false;
+        }
+        if rate.signum() != self.sign {
+            self.sign = rate.signum();
+            self.swings += 1;
+            self.last_swing = time;
+        }
+        self.swings >= SWINGS
+    }
+}
+
+// Recognizes nods (pitch oscillation) and shakes (yaw oscillation) from head angular velocity.
+pub struct GestureDetector {
+    // Angular speed in radians per second a swing has to exceed.
+    pub threshold: f32,
+    // Milliseconds after a gesture during which no other gesture fires.
+    pub cooldown_ms: f64,
+    nod: Oscillation,
+    shake: Oscillation,
+    cooldown_until: f64,
+}
+
+impl Default for GestureDetector {
+    fn default() -> Self {
+        GestureDetector {
+            threshold: 60f32.to_radians(),
+            cooldown_ms: 1000.,
+            nod: Oscillation::default(),
+            shake: Oscillation::default(),
+            cooldown_until: 0.,
+        }
+    }
+}
+
+impl GestureDetector {
+    pub fn reset(&mut self) {
+        self.nod = Oscillation::default();
+        self.shake = Oscillation::default();
+    }
+
+    // `head` is the viewer pose and `angular` its angular velocity, both in reference space.
+    pub fn update(&mut self, head: &Mat4, angular: &Vec3, time: f64) -> Option<HeadGesture> {
+        let pitch_rate = math::dot(angular, &[head[0], head[1], head[2]]);
+        let yaw_rate = angular[1];
+        // Only the dominant axis counts, so a diagonal movement isn't read as both.
+        let (nod_rate, shake_rate) = if pitch_rate.abs() > yaw_rate.abs() {
+            (pitch_rate, 0.)
+        } else {
+            (0., yaw_rate)
+        };
+        let nod = self.nod.update(nod_rate, self.threshold, time);
+        let shake = self.shake.update(shake_rate, self.threshold, time);
+        if time < self.cooldown_until {
+            return None;
+        }
+        let gesture = if nod {
+            HeadGesture::Nod
+        } else if shake {
+            HeadGesture::Shake
+        } else {
+            return None;
+        };
+        self.reset();
+        self.cooldown_until = time + self.cooldown_ms;
+        Some(gesture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frames 100 ms apart with the head facing -Z, so pitch is around X and yaw around Y.
+    fn run(detector: &mut GestureDetector, rates: &[Vec3], start: f64) -> Vec<HeadGesture> {
+        rates
+            .iter()
+            .enumerate()
+            .filter_map(|(i, angular)| {
+                detector.update(&math::IDENTITY, angular, start + i as f64 * 100.)
+            })
+            .collect()
+    }
+
+    fn swings(axis: Vec3, count: usize) -> Vec<Vec3> {
+        (0..count)
+            .map(|i| {
+                let sign = if i % 2 == 0 { 2. } else { -2. };
+                math::scale(&axis, sign)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn three_pitch_swings_are_a_nod() {
+        let mut detector = GestureDetector::default();
+        let gestures = run(&mut detector, &swings([1., 0., 0.], 3), 0.);
+        assert_eq!(gestures, vec![HeadGesture::Nod]);
+    }
+
+    #[test]
+    fn three_yaw_swings_are_a_shake() {
+        let mut detector = GestureDetector::default();
+        let gestures = run(&mut detector, &swings([0., 1., 0.], 3), 0.);
+        assert_eq!(gestures, vec![HeadGesture::Shake]);
+    }
+
+    #[test]
+    fn slow_movement_is_ignored() {
+        let mut detector = GestureDetector::default();
+        let rates: Vec<Vec3> = swings([0., 1., 0.], 6)
+            .iter()
+            .map(|rate| math::scale(rate, 0.1))
+            .collect();
+        assert!(run(&mut detector, &rates, 0.).is_empty());
+    }
+
+    #[test]
+    fn turning_one_way_is_not_a_shake() {
+        let mut detector = GestureDetector::default();
+        assert!(run(&mut detector, &[[0., 2., 0.]; 6], 0.).is_empty());
+    }
+
+    #[test]
+    fn swings_too_far_apart_start_over() {
+        let mut detector = GestureDetector::default();
+        let mut rates = swings([0., 1., 0.], 2);
+        // 700 ms without movement, longer than the swing window.
+        rates.extend_from_slice(&[[0., 0., 0.]; 7]);
+        rates.push([0., 2., 0.]);
+        assert!(run(&mut detector, &rates, 0.).is_empty());
+    }
+
+    #[test]
+    fn cooldown_suppresses_the_next_gesture() {
+        let mut detector = GestureDetector::default();
+        assert_eq!(run(&mut detector, &swings([1., 0., 0.], 3), 0.).len(), 1);
+        assert!(run(&mut detector, &swings([1., 0., 0.], 3), 300.).is_empty());
+        let later = run(&mut detector, &swings([1., 0., 0.], 3), 2000.);
+        assert_eq!(later, vec![HeadGesture::Nod]);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::math::{self, Vec3};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // The WebXR Hand Input Module isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrInputSource)]
+    type XrInputSourceHand;
+
+    // Undefined unless the hand-tracking feature is enabled and the source is a hand.
+    #[wasm_bindgen(method, getter)]
+    fn hand(this: &XrInputSourceHand) -> Option<XrHand>;
+
+    // Map-like, from joint name to XRJointSpace.
+    type XrHand;
+
+    #[wasm_bindgen(method)]
+    fn get(this: &XrHand, joint: &str) -> Option<XrSpace>;
+
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFrameHand;
+
+    #[wasm_bindgen(method, js_name = getJointPose)]
+    fn get_joint_pose(
+        this: &XrFrameHand,
+        joint: &XrSpace,
+        base_space: &XrSpace,
+    ) -> Option<XrJointPose>;
+
+    #[wasm_bindgen(extends = XrPose)]
+    type XrJointPose;
+
+    #[wasm_bindgen(method, getter)]
+    fn radius(this: &XrJointPose) -> f32;
+}
+
+// In the order of the XRHandJoint enum.
+pub const JOINTS: [&str; 25] = [
+    "wrist",
+    "thumb-metacarpal",
+    "thumb-phalanx-proximal",
+    "thumb-phalanx-distal",
+    "thumb-tip",
+    "index-finger-metacarpal",
+    "index-finger-phalanx-proximal",
+    "index-finger-phalanx-intermediate",
+    "index-finger-phalanx-distal",
+    "index-finger-tip",
+    "middle-finger-metacarpal",
+    "middle-finger-phalanx-proximal",
+    "middle-finger-phalanx-intermediate",
+    "middle-finger-phalanx-distal",
+    "middle-finger-tip",
+    "ring-finger-metacarpal",
+    "ring-finger-phalanx-proximal",
+    "ring-finger-phalanx-intermediate",
+    "ring-finger-phalanx-distal",
+    "ring-finger-tip",
+    "pinky-finger-metacarpal",
+    "pinky-finger-phalanx-proximal",
+    "pinky-finger-phalanx-intermediate",
+    "pinky-finger-phalanx-distal",
+    "pinky-finger-tip",
+];
+
+pub fn is_hand(input_source: &XrInputSource) -> bool {
+    input_source
+        .unchecked_ref::<XrInputSourceHand>()
+        .hand()
+        .is_some()
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JointPose {
+    pub matrix: Vec<f32>,
+    // Distance in meters from the joint to the skin.
+    pub radius: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HandPose {
+    pub handedness: String,
+    // Indexed like `JOINTS`, with null for joints that aren't tracked this frame.
+    pub joints: Vec<Option<JointPose>>,
+}
+
+impl HandPose {
+    // None for sources that aren't hands.
+    pub fn new(
+        frame: &XrFrame,
+        input_source: &XrInputSource,
+        ref_space: &XrReferenceSpace,
+        handedness: XrHandedness,
+    ) -> Option<HandPose> {
+        let hand = input_source.unchecked_ref::<XrInputSourceHand>().hand()?;
+        let frame = frame.unchecked_ref::<XrFrameHand>();
+        let joints = JOINTS
+            .iter()
+            .map(|joint| {
+                let pose = frame.get_joint_pose(&hand.get(joint)?, ref_space)?;
+                Some(JointPose {
+                    matrix: math::to_mat4(&pose.transform().matrix()).to_vec(),
+                    radius: pose.radius(),
+                })
+            })
+            .collect();
+        Some(HandPose {
+            handedness: JsValue::from(handedness).as_string().unwrap_or_default(),
+            joints,
+        })
+    }
+    // Position of the joint named as in `JOINTS`, None while it isn't tracked.
+    pub fn joint_position(&self, joint: &str) -> Option<Vec3> {
+        let index = JOINTS.iter().position(|name| *name == joint)?;
+        let matrix = &self.joints[index].as_ref()?.matrix;
+        Some([matrix[12], matrix[13], matrix[14]])
+    }
+}
@@ -0,0 +1,70 @@
+use crate::math::{self, Mat4};
+use js_sys::Promise;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // The WebXR Hit Test Module isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrSession)]
+    type XrSessionHitTest;
+
+    #[wasm_bindgen(method, catch, js_name = requestHitTestSource)]
+    fn request_hit_test_source(
+        this: &XrSessionHitTest,
+        options: &js_sys::Object,
+    ) -> Result<Promise, JsValue>;
+
+    pub type XrHitTestSource;
+
+    #[wasm_bindgen(method)]
+    pub fn cancel(this: &XrHitTestSource);
+
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFrameHitTest;
+
+    #[wasm_bindgen(method, js_name = getHitTestResults)]
+    fn get_hit_test_results(this: &XrFrameHitTest, source: &XrHitTestSource) -> js_sys::Array;
+
+    type XrHitTestResult;
+
+    #[wasm_bindgen(method, js_name = getPose)]
+    fn get_pose(this: &XrHitTestResult, base_space: &XrSpace) -> Option<XrPose>;
+}
+
+// Requests a hit test source casting along the viewer's forward ray, i.e. from the center of
+// the screen on handheld AR.
+pub async fn request_viewer_source(session: &XrSession) -> Result<XrHitTestSource, JsValue> {
+    let viewer_space = wasm_bindgen_futures::JsFuture::from(
+        session.request_reference_space(XrReferenceSpaceType::Viewer),
+    )
+    .await?;
+    let options = js_sys::Object::new();
+    js_sys::Reflect::set(&options, &JsValue::from("space"), &viewer_space)?;
+    let promise = session
+        .unchecked_ref::<XrSessionHitTest>()
+        .request_hit_test_source(&options)?;
+    Ok(wasm_bindgen_futures::JsFuture::from(promise)
+        .await?
+        .unchecked_into())
+}
+
+// Pose of the closest hit in `ref_space`, if the ray hits a surface this frame.
+pub fn first_hit(
+    frame: &XrFrame,
+    source: &XrHitTestSource,
+    ref_space: &XrReferenceSpace,
+) -> Option<Mat4> {
+    let results = frame
+        .unchecked_ref::<XrFrameHitTest>()
+        .get_hit_test_results(source);
+    // Results are sorted by distance from the ray origin.
+    let result = results.get(0);
+    if result.is_undefined() {
+        return None;
+    }
+    let result: XrHitTestResult = result.unchecked_into();
+    let pose = result.get_pose(ref_space)?;
+    Some(math::to_mat4(&pose.transform().matrix()))
+}
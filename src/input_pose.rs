@@ -0,0 +1,81 @@
+use crate::hands;
+use crate::lines::Line;
+use crate::math::{self, Mat4};
+use serde::Serialize;
+use wasm_bindgen::JsValue;
+use web_sys::*;
+
+// Debug ray drawn along each input source's target ray, in meters.
+const RAY_LENGTH: f32 = 2.;
+const RAY_WIDTH: f32 = 0.004;
+const RAY_COLOR: [f32; 3] = [1., 1., 1.];
+
+// Target ray of `input_source` in `ref_space`, tilted down by `pointer_tilt` radians. Screen
+// rays already point where the user tapped, so they are never tilted.
+pub fn target_ray(
+    frame: &XrFrame,
+    input_source: &XrInputSource,
+    ref_space: &XrReferenceSpace,
+    pointer_tilt: f32,
+) -> Option<Mat4> {
+    let pose = frame.get_pose(&input_source.target_ray_space(), ref_space)?;
+    let matrix = math::to_mat4(&pose.transform().matrix());
+    if input_source.target_ray_mode() == XrTargetRayMode::Screen {
+        return Some(matrix);
+    }
+    Some(math::multiply(&matrix, &math::rotation_x(-pointer_tilt)))
+}
+
+// Pose of one input source in the current reference space, as returned to JS.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputPose {
+    pub handedness: String,
+    // True for tracked hands, whose joints are in `XrApp::hand_poses`, false for controllers,
+    // screen and gaze input.
+    pub hand: bool,
+    // "screen" for handheld AR taps, whose target ray starts at the tapped point on the near
+    // plane and points into the scene, "tracked-pointer" for controllers and hands, or "gaze".
+    pub target_ray_mode: String,
+    pub target_ray: Vec<f32>,
+    // Screen and gaze input have no grip space, so there is no grip to report.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grip: Option<Vec<f32>>,
+}
+
+impl InputPose {
+    // None while the source's target ray isn't tracked.
+    pub fn new(
+        frame: &XrFrame,
+        input_source: &XrInputSource,
+        ref_space: &XrReferenceSpace,
+        handedness: XrHandedness,
+        pointer_tilt: f32,
+    ) -> Option<InputPose> {
+        let target_ray = target_ray(frame, input_source, ref_space, pointer_tilt)?;
+        let grip = input_source
+            .grip_space()
+            .and_then(|grip_space| frame.get_pose(&grip_space, ref_space))
+            .map(|pose| pose.transform().matrix());
+        Some(InputPose {
+            handedness: JsValue::from(handedness).as_string().unwrap_or_default(),
+            hand: hands::is_hand(input_source),
+            target_ray_mode: JsValue::from(input_source.target_ray_mode())
+                .as_string()
+                .unwrap_or_default(),
+            target_ray: target_ray.to_vec(),
+            grip,
+        })
+    }
+
+    // Line from the target ray origin along its -Z axis.
+    pub fn ray(&self) -> Line {
+        let target_ray = math::to_mat4(&self.target_ray);
+        Line {
+            start: math::transform_point(&target_ray, &[0., 0., 0.]),
+            end: math::transform_point(&target_ray, &[0., 0., -RAY_LENGTH]),
+            width: RAY_WIDTH,
+            color: RAY_COLOR,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use web_sys::*;
+
+// Interaction metaphor suited to the connected input, from most to least direct.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interaction {
+    DirectTouch,
+    Ray,
+    Tap,
+    Gaze,
+}
+
+impl Interaction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Interaction::DirectTouch => "direct-touch",
+            Interaction::Ray => "ray",
+            Interaction::Tap => "tap",
+            Interaction::Gaze => "gaze",
+        }
+    }
+}
+
+// What the recommendation needs from an XRInputSource.
+pub struct InputDescriptor {
+    pub target_ray_mode: XrTargetRayMode,
+    pub profiles: Vec<String>,
+}
+
+impl InputDescriptor {
+    pub fn from_source(source: &XrInputSource) -> InputDescriptor {
+        InputDescriptor {
+            target_ray_mode: source.target_ray_mode(),
+            profiles: source
+                .profiles()
+                .iter()
+                .filter_map(|value| value.as_string())
+                .collect(),
+        }
+    }
+
+    // Hand tracking reports profiles like "generic-hand" and "generic-hand-select".
+    fn is_hand(&self) -> bool {
+        self.profiles
+            .iter()
+            .any(|profile| profile.split('-').any(|part| part == "hand"))
+    }
+
+    fn interaction(&self) -> Interaction {
+        match self.target_ray_mode {
+            XrTargetRayMode::TrackedPointer if self.is_hand() => Interaction::DirectTouch,
+            XrTargetRayMode::TrackedPointer => Interaction::Ray,
+            XrTargetRayMode::Screen => Interaction::Tap,
+            _ => Interaction::Gaze,
+        }
+    }
+}
+
+// Picks the most direct metaphor any connected source supports, so a tracked hand wins over
+// a controller held in the other hand. With no input sources only gaze is left.
+pub fn recommend(sources: &[InputDescriptor]) -> Interaction {
+    let rank = |interaction: &Interaction| match interaction {
+        Interaction::DirectTouch => 0,
+        Interaction::Ray => 1,
+        Interaction::Tap => 2,
+        Interaction::Gaze => 3,
+    };
+    sources
+        .iter()
+        .map(InputDescriptor::interaction)
+        .min_by_key(rank)
+        .unwrap_or(Interaction::Gaze)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(target_ray_mode: XrTargetRayMode, profiles: &[&str]) -> InputDescriptor {
+        InputDescriptor {
+            target_ray_mode,
+            profiles: profiles
+                .iter()
+                .map(|profile| String::from(*profile))
+                .collect(),
+        }
+    }
+
+    fn controller() -> InputDescriptor {
+        source(
+            XrTargetRayMode::TrackedPointer,
+            &["oculus-touch-v3", "generic-trigger-squeeze-thumbstick"],
+        )
+    }
+
+    fn hand() -> InputDescriptor {
+        source(
+            XrTargetRayMode::TrackedPointer,
+            &["generic-hand-select", "generic-hand"],
+        )
+    }
+
+    #[test]
+    fn no_sources_leave_gaze() {
+        assert_eq!(recommend(&[]), Interaction::Gaze);
+    }
+
+    #[test]
+    fn each_source_maps_to_its_metaphor() {
+        assert_eq!(recommend(&[hand()]), Interaction::DirectTouch);
+        assert_eq!(recommend(&[controller()]), Interaction::Ray);
+        assert_eq!(
+            recommend(&[source(XrTargetRayMode::Screen, &[])]),
+            Interaction::Tap
+        );
+        assert_eq!(
+            recommend(&[source(XrTargetRayMode::Gaze, &[])]),
+            Interaction::Gaze
+        );
+    }
+
+    #[test]
+    fn hand_wins_over_a_controller() {
+        assert_eq!(recommend(&[controller(), hand()]), Interaction::DirectTouch);
+    }
+
+    #[test]
+    fn hand_is_matched_by_whole_profile_words() {
+        // "handheld" is not a hand.
+        let handheld = source(
+            XrTargetRayMode::TrackedPointer,
+            &["generic-handheld-remote"],
+        );
+        assert_eq!(recommend(&[handheld]), Interaction::Ray);
+    }
+
+    #[test]
+    fn metaphors_have_stable_names() {
+        assert_eq!(Interaction::DirectTouch.as_str(), "direct-touch");
+        assert_eq!(Interaction::Ray.as_str(), "ray");
+        assert_eq!(Interaction::Tap.as_str(), "tap");
+        assert_eq!(Interaction::Gaze.as_str(), "gaze");
+    }
+}
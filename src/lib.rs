@@ -1,3 +1,4 @@
+use futures::future::Either;
 use js_sys::Promise;
 use std::cell::RefCell;
 use std::collections::HashMap;
@@ -13,299 +14,2784 @@ macro_rules! log {
     }
 }
 
-fn request_animation_frame(session: &XrSession, f: &Closure<dyn FnMut(f64, XrFrame)>) -> i32 {
+mod analytics;
+mod anchors;
+mod blend;
+mod capabilities;
+mod config;
+mod convention;
+mod depth;
+mod drag_look;
+mod features;
+mod frame_rate;
+mod geometry;
+mod gesture;
+mod hands;
+mod hit_test;
+mod input_pose;
+mod interaction;
+mod lines;
+mod math;
+mod memory;
+mod mesh;
+mod multiview;
+mod obj;
+mod pinch;
+mod placement;
+mod planes;
+mod quality;
+mod renderers;
+mod select_repeat;
+mod session_mode;
+mod shader;
+mod stereo_debug;
+mod tap_feedback;
+mod timing;
+mod tracking;
+mod velocity;
+mod viewport;
+
+use analytics::SessionInfo;
+use anchors::Anchors;
+use blend::AlphaMode;
+use config::XrAppConfig;
+use convention::CoordinateConvention;
+use depth::DepthOcclusion;
+use drag_look::DragLook;
+use gesture::GestureDetector;
+use hands::HandPose;
+use hit_test::XrHitTestSource;
+use input_pose::InputPose;
+use interaction::InputDescriptor;
+use lines::{Line, LineRenderer, Lines};
+use math::Mat4;
+use memory::GpuMemory;
+use mesh::{Mesh, MeshRenderer, Meshes};
+use multiview::Multiview;
+use pinch::{PinchDetector, PinchEvent};
+use placement::Placement;
+use planes::PlaneOccluder;
+use quality::{
+    apply_xr_layer, AutoQuality, EyeScale, LayerOptions, QualityPreset, QualitySettings,
+};
+use renderers::Renderers;
+use select_repeat::SelectRepeat;
+use session_mode::{SessionMode, XrModes};
+use shader::{ProgramCache, UniformCache};
+use stereo_debug::StereoDebug;
+use tap_feedback::TapFeedback;
+use timing::FrameTiming;
+use tracking::TrackingLoss;
+use velocity::Velocity;
+use viewport::Viewport;
+
+const VERTEX_SHADER: &str = "#version 300 es
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+in vec3 vertexPosition;
+in vec3 vertexColor;
+out vec3 vColor;
+out vec3 vWorldPosition;
+out float vViewDepth;
+void main() {
+    vColor = vertexColor;
+    vec4 worldPosition = model * vec4(vertexPosition, 1.0);
+    vWorldPosition = worldPosition.xyz;
+    vec4 viewPosition = view * worldPosition;
+    vViewDepth = -viewPosition.z;
+    gl_Position = projection * viewPosition;
+}";
+
+const FRAGMENT_SHADER: &str = "#version 300 es
+precision highp float;
+uniform bool clipEnabled;
+uniform vec4 clipPlane;
+uniform bool depthEnabled;
+uniform sampler2D depthTexture;
+uniform mat4 depthUvTransform;
+uniform vec2 depthTexelWeights;
+in vec3 vColor;
+in vec3 vWorldPosition;
+in float vViewDepth;
+out vec4 fragmentColor;
+void main() {
+    if (clipEnabled && dot(clipPlane.xyz, vWorldPosition) < clipPlane.w) {
+        discard;
+    }
+    if (depthEnabled) {
+        vec2 uv = (depthUvTransform * vec4(gl_FragCoord.xy, 0, 1)).xy;
+        float realDepth = dot(texture(depthTexture, uv).ra, depthTexelWeights);
+        if (realDepth > 0.0 && realDepth < vViewDepth) {
+            discard;
+        }
+    }
+    fragmentColor = vec4(vColor,1);
+}";
+
+// Debug color for the border left by a viewport inset.
+const GUTTER_COLOR: [f32; 4] = [1., 0., 1., 1.];
+
+const DEFAULT_SESSION_TIMEOUT_MS: i32 = 10_000;
+
+type InputSourceEventClosure = Closure<dyn FnMut(XrInputSourceEvent)>;
+type SessionEventClosure = Closure<dyn FnMut(XrSessionEvent)>;
+type PointerEventClosure = Closure<dyn FnMut(PointerEvent)>;
+type InputSourcesChangeClosure = Closure<dyn FnMut(XrInputSourcesChangeEvent)>;
+type FrameClosure = Closure<dyn FnMut(f64, XrFrame)>;
+
+fn request_animation_frame(session: &XrSession, f: &FrameClosure) -> i32 {
     session.request_animation_frame(f.as_ref().unchecked_ref())
 }
 
+// Queues the next frame of the render loop, unless `end` ran in the meantime, such as from a
+// callback during this frame.
+fn request_next_frame(
+    session: &XrSession,
+    f: &RefCell<Option<FrameClosure>>,
+    running: &RefCell<bool>,
+    frame_handle: &RefCell<Option<i32>>,
+) {
+    if !*running.borrow() {
+        return;
+    }
+    if let Some(f) = f.borrow().as_ref() {
+        frame_handle.replace(Some(request_animation_frame(session, f)));
+    }
+}
+
+// Waits for the session request `promise`, failing with "session request timed out" if it
+// hasn't settled within `timeout_ms`. A session granted after that is ended right away so it
+// doesn't hold on to the headset. A timeout of 0 disables it.
+async fn with_timeout(promise: Promise, timeout_ms: i32) -> Result<JsValue, JsValue> {
+    if timeout_ms <= 0 {
+        return wasm_bindgen_futures::JsFuture::from(promise).await;
+    }
+    let window = web_sys::window().ok_or_else(|| JsValue::from("no window to time out in"))?;
+    let mut timer_result = Ok(0);
+    let timeout = Promise::new(&mut |_resolve, reject| {
+        timer_result = window.set_timeout_with_callback_and_timeout_and_arguments_1(
+            &reject,
+            timeout_ms,
+            &JsValue::from("session request timed out"),
+        );
+    });
+    let timer = timer_result?;
+    let request = wasm_bindgen_futures::JsFuture::from(promise.clone());
+    let timeout = wasm_bindgen_futures::JsFuture::from(timeout);
+    let result = match futures::future::select(request, timeout).await {
+        Either::Left((result, _)) => result,
+        Either::Right((result, _)) => {
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Ok(session) = wasm_bindgen_futures::JsFuture::from(promise).await {
+                    let session: XrSession = session.unchecked_into();
+                    if let Err(err) = wasm_bindgen_futures::JsFuture::from(session.end()).await {
+                        log!("error ending a session granted after the timeout:{:?}", err);
+                    }
+                }
+            });
+            result
+        }
+    };
+    window.clear_timeout_with_handle(timer);
+    result
+}
+
+// Handedness as reported to the app, with left and right swapped to work around runtimes
+// that report them the wrong way round.
+fn handedness(input_source: &XrInputSource, swap: bool) -> XrHandedness {
+    match (input_source.handedness(), swap) {
+        (XrHandedness::Left, true) => XrHandedness::Right,
+        (XrHandedness::Right, true) => XrHandedness::Left,
+        (handedness, _) => handedness,
+    }
+}
+
+fn call_select_callback(
+    callback: &RefCell<Option<js_sys::Function>>,
+    frame: &XrFrame,
+    input_source: &XrInputSource,
+    ref_space: &XrReferenceSpace,
+    pointer_tilt: f32,
+    swap_handedness: bool,
+) {
+    let callback = if let Some(callback) = callback.borrow().as_ref() {
+        callback.clone()
+    } else {
+        return;
+    };
+
+    let (target_ray, grip) = {
+        let target_ray = input_pose::target_ray(frame, input_source, ref_space, pointer_tilt)
+            .map_or(JsValue::UNDEFINED, |matrix| {
+                js_sys::Float32Array::from(&matrix[..]).into()
+            });
+        let grip = input_source
+            .grip_space()
+            .and_then(|grip_space| frame.get_pose(&grip_space, ref_space))
+            .map_or(JsValue::UNDEFINED, |pose| {
+                js_sys::Float32Array::from(&pose.transform().matrix()[..]).into()
+            });
+        (target_ray, grip)
+    };
+
+    if let Err(err) = callback.call3(
+        &JsValue::NULL,
+        &handedness(input_source, swap_handedness).into(),
+        &target_ray,
+        &grip,
+    ) {
+        log!("select callback error:{:?}", err);
+    }
+}
+
+// Confirms the placement with the last hit, if one is being aimed, and attaches its mesh to an
+// anchor created there, reporting the anchor to `on_placed`.
+fn confirm_placement(
+    placement: &Rc<RefCell<Option<Placement>>>,
+    hit_pose: &RefCell<Option<Mat4>>,
+    anchors: &RefCell<Anchors>,
+    on_placed: &Rc<RefCell<Option<js_sys::Function>>>,
+) {
+    let hit = *hit_pose.borrow();
+    let pose = match placement.borrow_mut().as_mut().and_then(|p| p.confirm(hit)) {
+        Some(pose) => pose,
+        None => return,
+    };
+    let promise = anchors.borrow_mut().request(pose);
+    let placement = placement.clone();
+    let on_placed = on_placed.clone();
+    wasm_bindgen_futures::spawn_local(async move {
+        let anchor = wasm_bindgen_futures::JsFuture::from(promise)
+            .await
+            .inspect_err(|err| {
+                log!("placement anchor error:{:?}", err);
+            })
+            .ok()
+            .and_then(|id| id.as_f64())
+            .map(|id| id as u32);
+        // Placement may have been turned off or restarted in the meantime.
+        let anchored = placement
+            .borrow_mut()
+            .as_mut()
+            .is_some_and(|placement| placement.anchored(anchor));
+        let callback = on_placed.borrow().clone();
+        if let (true, Some(anchor), Some(callback)) = (anchored, anchor, callback) {
+            let pose = js_sys::Float32Array::from(&pose[..]);
+            if let Err(err) = callback.call2(&JsValue::NULL, &anchor.into(), &pose) {
+                log!("placed callback error:{:?}", err);
+            }
+        }
+    });
+}
+
+// Transient sources such as AR screen taps exist only for the duration of the select and
+// report a handedness of "none".
+fn call_handedness_callback(
+    callback: &RefCell<Option<js_sys::Function>>,
+    input_source: &XrInputSource,
+    swap_handedness: bool,
+) {
+    let callback = callback.borrow().clone();
+    if let Some(callback) = callback {
+        let handedness = handedness(input_source, swap_handedness);
+        if let Err(err) = callback.call1(&JsValue::NULL, &handedness.into()) {
+            log!("select callback error:{:?}", err);
+        }
+    }
+}
+
+fn call_input_source_callback(
+    callback: &RefCell<Option<js_sys::Function>>,
+    sources: &[XrInputSource],
+) {
+    let callback = if let Some(callback) = callback.borrow().as_ref() {
+        callback.clone()
+    } else {
+        return;
+    };
+    for source in sources {
+        if let Err(err) = callback.call1(&JsValue::NULL, source) {
+            log!("input source callback error:{:?}", err);
+        }
+    }
+}
+
+struct ViewCamera {
+    view: Vec<f32>,
+    projection: Vec<f32>,
+    viewport: Viewport,
+}
+
+// Works for any number of views (mono inline, stereo, secondary views); eye is not assumed.
+fn per_view_camera(gl_layer: &XrWebGlLayer, view: &XrView, mirror: bool) -> Option<ViewCamera> {
+    let projection = view.projection_matrix();
+    let projection = if mirror {
+        math::mirror_x(&math::to_mat4(&projection)).to_vec()
+    } else {
+        projection
+    };
+    Some(ViewCamera {
+        view: view.transform().inverse().matrix(),
+        projection,
+        viewport: Viewport::from_xr(&gl_layer.get_viewport(view)?),
+    })
+}
+
+// `custom` if set, otherwise transparent for AR so the passthrough shows wherever nothing is
+// drawn, and opaque black for everything else.
+fn clear_color(session_mode: SessionMode, custom: Option<[f32; 4]>) -> [f32; 4] {
+    match custom {
+        Some(color) => color,
+        None if session_mode == SessionMode::ImmersiveAr => [0., 0., 0., 0.],
+        None => [0., 0., 0., 1.],
+    }
+}
+
+// Fills the part of `outer` outside `inner` with GUTTER_COLOR and clears `inner` to
+// `clear_color`.
+fn clear_gutter(
+    gl: &WebGl2RenderingContext,
+    outer: &Viewport,
+    inner: &Viewport,
+    clear_color: [f32; 4],
+) {
+    gl.enable(WebGl2RenderingContext::SCISSOR_TEST);
+    gl.scissor(outer.x, outer.y, outer.width, outer.height);
+    let [r, g, b, a] = GUTTER_COLOR;
+    gl.clear_color(r, g, b, a);
+    gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    gl.scissor(inner.x, inner.y, inner.width, inner.height);
+    let [r, g, b, a] = clear_color;
+    gl.clear_color(r, g, b, a);
+    gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    gl.disable(WebGl2RenderingContext::SCISSOR_TEST);
+}
+
+fn recenter_reference_space(
+    frame: &XrFrame,
+    ref_space: &XrReferenceSpace,
+    spawn: &Mat4,
+) -> Option<XrReferenceSpace> {
+    let pose = frame.get_viewer_pose(ref_space)?;
+    let head = math::to_mat4(&pose.transform().matrix());
+    let (position, angle) = math::yaw_alignment_offset(&head, spawn);
+
+    let mut offset_position = DomPointInit::new();
+    offset_position
+        .x(position[0] as f64)
+        .y(position[1] as f64)
+        .z(position[2] as f64);
+    let mut offset_orientation = DomPointInit::new();
+    offset_orientation
+        .y((angle / 2.).sin() as f64)
+        .w((angle / 2.).cos() as f64);
+    let offset =
+        XrRigidTransform::new_with_position_and_orientation(&offset_position, &offset_orientation)
+            .ok()?;
+
+    Some(ref_space.get_offset_reference_space(&offset))
+}
+
+// Poses from before and after a reference space swap aren't comparable, so anything estimated
+// from consecutive poses starts over.
+fn reset_head_motion(head_velocity: &RefCell<Velocity>, gestures: &RefCell<GestureDetector>) {
+    head_velocity.replace(Velocity::default());
+    gestures.borrow_mut().reset();
+}
+
+// Swaps `ref_space` for the next simpler reference space type after prolonged tracking loss,
+// calling `on_swap` once the new space is in place.
+fn fall_back_reference_space<F: FnOnce() + 'static>(
+    session: &XrSession,
+    ref_space: Rc<RefCell<Option<XrReferenceSpace>>>,
+    ref_space_type: Rc<RefCell<XrReferenceSpaceType>>,
+    tracking_loss: Rc<RefCell<Option<TrackingLoss>>>,
+    on_reference_space_change: Rc<RefCell<Option<js_sys::Function>>>,
+    on_swap: F,
+) {
+    let space_type = if let Some(space_type) = tracking::fallback(*ref_space_type.borrow()) {
+        space_type
+    } else {
+        return;
+    };
+    // Even if the request fails, the next attempt moves further down the ladder.
+    ref_space_type.replace(space_type);
+    let ref_space_promise = session.request_reference_space(space_type);
+
+    wasm_bindgen_futures::spawn_local(async move {
+        let result = wasm_bindgen_futures::JsFuture::from(ref_space_promise).await;
+        if let Some(tracking_loss) = tracking_loss.borrow_mut().as_mut() {
+            tracking_loss.pending = false;
+        }
+        match result {
+            Ok(xr_ref_space) => {
+                ref_space.replace(Some(xr_ref_space.into()));
+                on_swap();
+                let callback = on_reference_space_change.borrow().clone();
+                if let Some(callback) = callback {
+                    if let Err(err) = callback.call1(&JsValue::NULL, &space_type.into()) {
+                        log!("reference space callback error:{:?}", err);
+                    }
+                }
+            }
+            Err(err) => {
+                log!("reference space fallback error:{:?}", err);
+            }
+        }
+    });
+}
+
+// Triangulates a simple polygon given as flat [x0, y0, x1, y1, ...] coordinates into
+// counter-clockwise triangle indices.
+#[wasm_bindgen]
+pub fn triangulate_polygon(points: &[f32]) -> Result<Vec<u16>, JsValue> {
+    let points: Vec<math::Vec2> = points
+        .chunks_exact(2)
+        .map(|point| [point[0], point[1]])
+        .collect();
+    geometry::triangulate(&points).map_err(JsValue::from)
+}
+
+// Column-major model matrix that scales, then rotates by the quaternion [x, y, z, w], then
+// translates, for `set_mesh_transform`.
+#[wasm_bindgen]
+pub fn compose_transform(
+    position: &[f32],
+    orientation: &[f32],
+    scale: &[f32],
+) -> Result<Vec<f32>, JsValue> {
+    match (position, orientation, scale) {
+        ([x, y, z], [qx, qy, qz, qw], [sx, sy, sz]) => {
+            let rotation_scale = math::multiply(
+                &math::rotation_quat(*qx, *qy, *qz, *qw),
+                &math::scaling(*sx, *sy, *sz),
+            );
+            Ok(math::multiply(&math::translation(*x, *y, *z), &rotation_scale).to_vec())
+        }
+        _ => Err(JsValue::from(
+            "position and scale need 3 floats and orientation needs 4",
+        )),
+    }
+}
+
+// Creates a canvas for the context, appended to the element with id `mount_id` if given and
+// left out of the page otherwise.
 #[wasm_bindgen]
-pub fn create_webgl_context(xr_mode: bool) -> Result<WebGl2RenderingContext, JsValue> {
-    let canvas = web_sys::window()
-        .unwrap()
-        .document()
-        .unwrap()
-        .create_element("canvas")
-        .unwrap()
-        .dyn_into::<HtmlCanvasElement>()
-        .unwrap();
-
-    let gl: WebGl2RenderingContext = if xr_mode {
+pub fn create_webgl_context(
+    xr_mode: bool,
+    mount_id: Option<String>,
+) -> Result<WebGl2RenderingContext, JsValue> {
+    let document = web_sys::window()
+        .and_then(|window| window.document())
+        .ok_or_else(|| JsValue::from("no document to create a canvas in"))?;
+    let canvas = document
+        .create_element("canvas")?
+        .dyn_into::<HtmlCanvasElement>()?;
+    if let Some(mount_id) = mount_id {
+        document
+            .get_element_by_id(&mount_id)
+            .ok_or_else(|| JsValue::from(format!("no element with id {}", mount_id)))?
+            .append_child(&canvas)?;
+    }
+
+    create_webgl_context_for_canvas(&canvas, xr_mode)
+}
+
+#[wasm_bindgen]
+pub fn create_webgl_context_for_canvas(
+    canvas: &HtmlCanvasElement,
+    xr_mode: bool,
+) -> Result<WebGl2RenderingContext, JsValue> {
+    let gl = if xr_mode {
         let mut gl_attribs = HashMap::new();
         gl_attribs.insert(String::from("xrCompatible"), true);
         let js_gl_attribs = JsValue::from_serde(&gl_attribs).unwrap();
 
-        canvas
-            .get_context_with_context_options("webgl2", &js_gl_attribs)?
-            .unwrap()
-            .dyn_into()?
-    } else {
-        canvas.get_context("webgl2")?.unwrap().dyn_into()?
-    };
+        canvas.get_context_with_context_options("webgl2", &js_gl_attribs)?
+    } else {
+        canvas.get_context("webgl2")?
+    };
+    // null when WebGL2 is unsupported or disabled.
+    let gl: WebGl2RenderingContext = gl
+        .ok_or_else(|| JsValue::from("WebGL2 is not available"))?
+        .dyn_into()?;
+
+    Ok(gl)
+}
+
+// Immersive sessions render into the layer's framebuffer, not the canvas, but a canvas on the
+// page should still match the resolution being rendered.
+fn size_canvas_to_layer(gl: &WebGl2RenderingContext, layer: &XrWebGlLayer) {
+    if let Some(canvas) = gl
+        .canvas()
+        .and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok())
+    {
+        canvas.set_width(layer.framebuffer_width());
+        canvas.set_height(layer.framebuffer_height());
+    }
+}
+
+// Inline sessions render into the canvas itself, so keep its drawing buffer at the size it is
+// displayed at.
+fn fit_canvas_to_display(gl: &WebGl2RenderingContext) {
+    let canvas = if let Some(canvas) = gl
+        .canvas()
+        .and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok())
+    {
+        canvas
+    } else {
+        return;
+    };
+    let ratio = web_sys::window().map_or(1., |window| window.device_pixel_ratio());
+    let width = (canvas.client_width() as f64 * ratio) as u32;
+    let height = (canvas.client_height() as f64 * ratio) as u32;
+    if canvas.width() != width || canvas.height() != height {
+        canvas.set_width(width);
+        canvas.set_height(height);
+    }
+}
+
+// Cloning shares every field, for session handlers that need the whole app.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct XrApp {
+    session: Rc<RefCell<Option<XrSession>>>,
+    ref_space: Rc<RefCell<Option<XrReferenceSpace>>>,
+    gl: Rc<WebGl2RenderingContext>,
+    quality: Rc<RefCell<QualitySettings>>,
+    // View and projection of the first view drawn in the last frame, for `screen_to_ray`.
+    last_view: Rc<RefCell<Option<(Mat4, Mat4)>>>,
+    auto_recenter: Rc<RefCell<Option<Mat4>>>,
+    lines: Rc<RefCell<Lines>>,
+    on_select: Rc<RefCell<Option<js_sys::Function>>>,
+    input_event_closures: Rc<RefCell<Vec<InputSourceEventClosure>>>,
+    pointer_tilt: Rc<RefCell<f32>>,
+    program_cache: Rc<RefCell<ProgramCache>>,
+    clip_plane: Rc<RefCell<Option<[f32; 4]>>>,
+    input_sources: Rc<RefCell<Vec<XrInputSource>>>,
+    on_input_added: Rc<RefCell<Option<js_sys::Function>>>,
+    on_input_removed: Rc<RefCell<Option<js_sys::Function>>>,
+    input_sources_change_closure: Rc<RefCell<Option<InputSourcesChangeClosure>>>,
+    session_timeout_ms: Rc<RefCell<i32>>,
+    gpu_memory: Rc<RefCell<GpuMemory>>,
+    select_repeat: Rc<RefCell<Option<SelectRepeat>>>,
+    frame_timing: Rc<RefCell<FrameTiming>>,
+    head_velocity: Rc<RefCell<Velocity>>,
+    gestures: Rc<RefCell<GestureDetector>>,
+    on_gesture: Rc<RefCell<Option<js_sys::Function>>>,
+    session_info: Rc<RefCell<Option<SessionInfo>>>,
+    on_session_start: Rc<RefCell<Option<js_sys::Function>>>,
+    on_session_end: Rc<RefCell<Option<js_sys::Function>>>,
+    session_end_closure: Rc<RefCell<Option<SessionEventClosure>>>,
+    session_mode: Rc<RefCell<SessionMode>>,
+    auto_quality: Rc<RefCell<Option<AutoQuality>>>,
+    on_quality_change: Rc<RefCell<Option<js_sys::Function>>>,
+    stereo_debug: Rc<RefCell<bool>>,
+    ref_space_type: Rc<RefCell<XrReferenceSpaceType>>,
+    tracking_loss: Rc<RefCell<Option<TrackingLoss>>>,
+    on_reference_space_change: Rc<RefCell<Option<js_sys::Function>>>,
+    eye_scale: Rc<RefCell<EyeScale>>,
+    premultiplied_alpha: Rc<RefCell<Option<bool>>>,
+    mirror_horizontal: Rc<RefCell<bool>>,
+    viewport_inset: Rc<RefCell<i32>>,
+    uniforms: Rc<RefCell<UniformCache>>,
+    swap_handedness: Rc<RefCell<bool>>,
+    running: Rc<RefCell<bool>>,
+    frame_handle: Rc<RefCell<Option<i32>>>,
+    viewer_pose: Rc<RefCell<Option<Mat4>>>,
+    meshes: Rc<RefCell<Meshes>>,
+    program: Rc<RefCell<Option<WebGlProgram>>>,
+    on_frame: Rc<RefCell<Option<js_sys::Function>>>,
+    input_poses: Rc<RefCell<Vec<InputPose>>>,
+    controller_rays: Rc<RefCell<bool>>,
+    on_select_start: Rc<RefCell<Option<js_sys::Function>>>,
+    on_select_end: Rc<RefCell<Option<js_sys::Function>>>,
+    visible: Rc<RefCell<bool>>,
+    visibility_change_closure: Rc<RefCell<Option<SessionEventClosure>>>,
+    depth_test: Rc<RefCell<bool>>,
+    depth_func: Rc<RefCell<u32>>,
+    hit_test_source: Rc<RefCell<Option<XrHitTestSource>>>,
+    hit_pose: Rc<RefCell<Option<Mat4>>>,
+    anchors: Rc<RefCell<Anchors>>,
+    hand_poses: Rc<RefCell<Vec<HandPose>>>,
+    custom_clear_color: Rc<RefCell<Option<[f32; 4]>>>,
+    drag_look: Rc<RefCell<DragLook>>,
+    drag_look_closures: Rc<RefCell<Vec<PointerEventClosure>>>,
+    on_visibility_change: Rc<RefCell<Option<js_sys::Function>>>,
+    on_input_sources_change: Rc<RefCell<Option<js_sys::Function>>>,
+    layer_options: Rc<RefCell<LayerOptions>>,
+    on_tracking_lost: Rc<RefCell<Option<js_sys::Function>>>,
+    multiview: Rc<RefCell<bool>>,
+    // Enabled, source and destination factors; None blends according to the alpha mode.
+    blending: Rc<RefCell<Option<(bool, u32, u32)>>>,
+    config: XrAppConfig,
+    pinches: Rc<RefCell<PinchDetector>>,
+    on_pinch_start: Rc<RefCell<Option<js_sys::Function>>>,
+    on_pinch_end: Rc<RefCell<Option<js_sys::Function>>>,
+    plane_occlusion: Rc<RefCell<bool>>,
+    tap_feedback: Rc<RefCell<TapFeedback>>,
+    depth_occlusion: Rc<RefCell<bool>>,
+    placement: Rc<RefCell<Option<Placement>>>,
+    on_placed: Rc<RefCell<Option<js_sys::Function>>>,
+    renderers: Rc<RefCell<Option<Renderers>>>,
+    // The spawn transform of the last applied recenter, re-applied after a reference space
+    // fallback.
+    recenter_spawn: Rc<RefCell<Option<Mat4>>>,
+    // The framebuffer scale given to the last `init`, applied over every quality preset.
+    framebuffer_scale: Rc<RefCell<Option<f64>>>,
+}
+
+#[wasm_bindgen]
+impl XrApp {
+    // `mount_id` is the id of an element to append the canvas to; without it the canvas stays
+    // off the page. Everything else uses the defaults, see `with_config`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(mount_id: Option<String>) -> Result<XrApp, JsValue> {
+        let mut config = XrAppConfig::new();
+        config.mount_id = mount_id;
+        XrApp::with_config(config)
+    }
+
+    // Creates the app with the session and rendering settings in `config`, which `init` uses
+    // for any argument it isn't given.
+    pub fn with_config(config: XrAppConfig) -> Result<XrApp, JsValue> {
+        let xr_mode = true;
+        let gl = create_webgl_context(xr_mode, config.mount_id.clone())?;
+        Ok(XrApp::with_context_and_config(gl, config))
+    }
+
+    // Renders into `canvas` instead of an offscreen canvas, which inline sessions need to be
+    // visible on the page.
+    pub fn with_canvas(canvas: &HtmlCanvasElement) -> Result<XrApp, JsValue> {
+        XrApp::with_canvas_and_config(canvas, XrAppConfig::new())
+    }
+
+    // `with_canvas` with the settings in `config`, as in `with_config`. The config's mount id
+    // is ignored since `canvas` is already placed.
+    pub fn with_canvas_and_config(
+        canvas: &HtmlCanvasElement,
+        config: XrAppConfig,
+    ) -> Result<XrApp, JsValue> {
+        let xr_mode = true;
+        let gl = create_webgl_context_for_canvas(canvas, xr_mode)?;
+        Ok(XrApp::with_context_and_config(gl, config))
+    }
+
+    fn with_context_and_config(gl: WebGl2RenderingContext, config: XrAppConfig) -> XrApp {
+        let mut app = XrApp::with_context(gl);
+        if let Some([r, g, b, a]) = config.clear_color {
+            app.set_clear_color(r, g, b, a);
+        }
+        app.config = config;
+        app
+    }
+
+    fn with_context(gl: WebGl2RenderingContext) -> XrApp {
+        console_error_panic_hook::set_once();
+        let session = Rc::new(RefCell::new(None));
+        let ref_space = Rc::new(RefCell::new(None));
+
+        let gl = Rc::new(gl);
+        let quality = Rc::new(RefCell::new(QualitySettings::default()));
+        let last_view = Rc::new(RefCell::new(None));
+        let auto_recenter = Rc::new(RefCell::new(None));
+        let lines = Rc::new(RefCell::new(Lines::default()));
+        let on_select = Rc::new(RefCell::new(None));
+        let input_event_closures = Rc::new(RefCell::new(Vec::new()));
+        let pointer_tilt = Rc::new(RefCell::new(0.));
+        let program_cache = Rc::new(RefCell::new(ProgramCache::default()));
+        let clip_plane = Rc::new(RefCell::new(None));
+        let input_sources = Rc::new(RefCell::new(Vec::new()));
+        let on_input_added = Rc::new(RefCell::new(None));
+        let on_input_removed = Rc::new(RefCell::new(None));
+        let input_sources_change_closure = Rc::new(RefCell::new(None));
+        let session_timeout_ms = Rc::new(RefCell::new(DEFAULT_SESSION_TIMEOUT_MS));
+        let gpu_memory = Rc::new(RefCell::new(GpuMemory::default()));
+        let select_repeat = Rc::new(RefCell::new(None));
+        let frame_timing = Rc::new(RefCell::new(FrameTiming::default()));
+        let head_velocity = Rc::new(RefCell::new(Velocity::default()));
+        let gestures = Rc::new(RefCell::new(GestureDetector::default()));
+        let on_gesture = Rc::new(RefCell::new(None));
+        let session_info = Rc::new(RefCell::new(None));
+        let on_session_start = Rc::new(RefCell::new(None));
+        let on_session_end = Rc::new(RefCell::new(None));
+        let session_end_closure = Rc::new(RefCell::new(None));
+        let session_mode = Rc::new(RefCell::new(SessionMode::ImmersiveVr));
+        let auto_quality = Rc::new(RefCell::new(None));
+        let on_quality_change = Rc::new(RefCell::new(None));
+        let stereo_debug = Rc::new(RefCell::new(false));
+        let ref_space_type = Rc::new(RefCell::new(XrReferenceSpaceType::BoundedFloor));
+        let tracking_loss = Rc::new(RefCell::new(None));
+        let on_reference_space_change = Rc::new(RefCell::new(None));
+        let eye_scale = Rc::new(RefCell::new(EyeScale::default()));
+        let premultiplied_alpha = Rc::new(RefCell::new(None));
+        let mirror_horizontal = Rc::new(RefCell::new(false));
+        let viewport_inset = Rc::new(RefCell::new(0));
+        let uniforms = Rc::new(RefCell::new(UniformCache::default()));
+        let swap_handedness = Rc::new(RefCell::new(false));
+        let running = Rc::new(RefCell::new(false));
+        let frame_handle = Rc::new(RefCell::new(None));
+        let viewer_pose = Rc::new(RefCell::new(None));
+        let meshes = Rc::new(RefCell::new(Meshes::default()));
+        let program = Rc::new(RefCell::new(None));
+        let on_frame = Rc::new(RefCell::new(None));
+        let input_poses = Rc::new(RefCell::new(Vec::new()));
+        let controller_rays = Rc::new(RefCell::new(false));
+        let on_select_start = Rc::new(RefCell::new(None));
+        let on_select_end = Rc::new(RefCell::new(None));
+        let visible = Rc::new(RefCell::new(true));
+        let visibility_change_closure = Rc::new(RefCell::new(None));
+        let depth_test = Rc::new(RefCell::new(true));
+        let depth_func = Rc::new(RefCell::new(WebGl2RenderingContext::LESS));
+        let hit_test_source = Rc::new(RefCell::new(None));
+        let hit_pose = Rc::new(RefCell::new(None));
+        let anchors = Rc::new(RefCell::new(Anchors::default()));
+        let hand_poses = Rc::new(RefCell::new(Vec::new()));
+        let custom_clear_color = Rc::new(RefCell::new(None));
+        let drag_look = Rc::new(RefCell::new(DragLook::default()));
+        let drag_look_closures = Rc::new(RefCell::new(Vec::new()));
+        let on_visibility_change = Rc::new(RefCell::new(None));
+        let on_input_sources_change = Rc::new(RefCell::new(None));
+        let layer_options = Rc::new(RefCell::new(LayerOptions::default()));
+        let on_tracking_lost = Rc::new(RefCell::new(None));
+        let multiview = Rc::new(RefCell::new(false));
+        let blending = Rc::new(RefCell::new(None));
+        let pinches = Rc::new(RefCell::new(PinchDetector::default()));
+        let on_pinch_start = Rc::new(RefCell::new(None));
+        let on_pinch_end = Rc::new(RefCell::new(None));
+        let plane_occlusion = Rc::new(RefCell::new(false));
+        let tap_feedback = Rc::new(RefCell::new(TapFeedback::default()));
+        let depth_occlusion = Rc::new(RefCell::new(false));
+        let placement = Rc::new(RefCell::new(None));
+        let on_placed = Rc::new(RefCell::new(None));
+        let renderers = Rc::new(RefCell::new(None));
+        let recenter_spawn = Rc::new(RefCell::new(None));
+        let framebuffer_scale = Rc::new(RefCell::new(None));
+
+        XrApp {
+            session,
+            ref_space,
+            gl,
+            quality,
+            last_view,
+            auto_recenter,
+            lines,
+            on_select,
+            input_event_closures,
+            pointer_tilt,
+            program_cache,
+            clip_plane,
+            input_sources,
+            on_input_added,
+            on_input_removed,
+            input_sources_change_closure,
+            session_timeout_ms,
+            gpu_memory,
+            select_repeat,
+            frame_timing,
+            head_velocity,
+            gestures,
+            on_gesture,
+            session_info,
+            on_session_start,
+            on_session_end,
+            session_end_closure,
+            session_mode,
+            auto_quality,
+            on_quality_change,
+            stereo_debug,
+            ref_space_type,
+            tracking_loss,
+            on_reference_space_change,
+            eye_scale,
+            premultiplied_alpha,
+            mirror_horizontal,
+            viewport_inset,
+            uniforms,
+            swap_handedness,
+            running,
+            frame_handle,
+            viewer_pose,
+            meshes,
+            program,
+            on_frame,
+            input_poses,
+            controller_rays,
+            on_select_start,
+            on_select_end,
+            visible,
+            visibility_change_closure,
+            depth_test,
+            depth_func,
+            hit_test_source,
+            hit_pose,
+            anchors,
+            hand_poses,
+            custom_clear_color,
+            drag_look,
+            drag_look_closures,
+            on_visibility_change,
+            on_input_sources_change,
+            layer_options,
+            on_tracking_lost,
+            multiview,
+            blending,
+            config: XrAppConfig::default(),
+            pinches,
+            on_pinch_start,
+            on_pinch_end,
+            plane_occlusion,
+            tap_feedback,
+            depth_occlusion,
+            placement,
+            on_placed,
+            renderers,
+            recenter_spawn,
+            framebuffer_scale,
+        }
+    }
+
+    pub fn set_quality_preset(&self, preset: QualityPreset) -> Result<(), JsValue> {
+        let settings = QualitySettings::from_preset(preset);
+        self.quality.replace(settings);
+
+        if let Some(sess) = self.session.borrow().as_ref() {
+            let layer = apply_xr_layer(
+                sess,
+                &self.gl,
+                &settings,
+                &self.layer_options.borrow(),
+                *self.framebuffer_scale.borrow(),
+            )?;
+            if *self.session_mode.borrow() != SessionMode::Inline {
+                size_canvas_to_layer(&self.gl, &layer);
+            }
+        }
+        Ok(())
+    }
+
+    // Scale of the XR layer framebuffer relative to the runtime's default size.
+    pub fn framebuffer_scale(&self) -> f64 {
+        self.quality
+            .borrow()
+            .layer_scale(*self.framebuffer_scale.borrow())
+    }
+
+    // Scale that would match the display's native resolution, which is often above 1 since
+    // runtimes default to a smaller framebuffer. None without a session.
+    pub fn native_framebuffer_scale(&self) -> Option<f64> {
+        self.session
+            .borrow()
+            .as_ref()
+            .map(XrWebGlLayer::get_native_framebuffer_scale_factor)
+    }
+
+    // Blends with ONE/ONE_MINUS_SRC_ALPHA, which expects shaders to output color multiplied by
+    // alpha, instead of SRC_ALPHA/ONE_MINUS_SRC_ALPHA. Undefined picks premultiplied alpha
+    // automatically for sessions with the "alpha-blend" environment blend mode.
+    pub fn set_premultiplied_alpha(&self, enabled: Option<bool>) {
+        self.premultiplied_alpha.replace(enabled);
+    }
+
+    // Turns blending on or off with the given `blend_func` factors, e.g. `gl.ONE` and
+    // `gl.ONE_MINUS_SRC_ALPHA`, overriding `set_premultiplied_alpha`. Blending is on by default.
+    pub fn set_blending(&self, enabled: bool, src: u32, dst: u32) -> Result<(), JsValue> {
+        if !blend::is_blend_factor(src, true) {
+            return Err(JsValue::from(format!("not a source blend factor: {}", src)));
+        }
+        if !blend::is_blend_factor(dst, false) {
+            return Err(JsValue::from(format!(
+                "not a destination blend factor: {}",
+                dst
+            )));
+        }
+        self.blending.replace(Some((enabled, src, dst)));
+        Ok(())
+    }
+
+    // Mirrors the rendered image left to right so content lines up with a mirrored
+    // front-camera preview in selfie AR.
+    pub fn set_mirror_horizontal(&self, enabled: bool) {
+        self.mirror_horizontal.replace(enabled);
+    }
+
+    // Shrinks each eye's viewport by `pixels` on every side and fills the border with magenta,
+    // for lining the image up with lens correction grids. 0 renders normally.
+    pub fn set_viewport_inset(&self, pixels: i32) {
+        self.viewport_inset.replace(pixels.max(0));
+    }
+
+    // Main program uniform uploads since start: [uploaded, skipped because unchanged].
+    pub fn uniform_upload_stats(&self) -> Vec<u32> {
+        let uniforms = self.uniforms.borrow();
+        vec![uniforms.uploads, uniforms.skipped]
+    }
+
+    // Re-uploads every main program uniform on the next frame, even if unchanged.
+    pub fn force_uniform_upload(&self) {
+        self.uniforms.borrow_mut().invalidate();
+    }
+
+    // Renders each eye at a fraction of its full viewport resolution, clamped to [0.5, 1].
+    // Strongly asymmetric scales can be uncomfortable. Needs runtime support for dynamic
+    // viewport scaling; otherwise both eyes render at full resolution.
+    pub fn set_eye_scale(&self, left: f32, right: f32) {
+        self.eye_scale.replace(EyeScale::new(left, right));
+    }
+
+    // Steps the quality preset down, never below `floor`, when frames keep taking longer than
+    // `budget_ms`. A budget of 0 or less turns it off.
+    pub fn set_auto_quality(&self, budget_ms: f64, floor: QualityPreset) {
+        self.auto_quality.replace(if budget_ms > 0. {
+            Some(AutoQuality::new(budget_ms, floor))
+        } else {
+            None
+        });
+    }
+
+    // Called with the new QualityPreset after auto quality steps down.
+    pub fn set_on_quality_change(&self, callback: Option<js_sys::Function>) {
+        self.on_quality_change.replace(callback);
+    }
+
+    // World-space ray through normalized screen coordinates (origin at the top left) as
+    // [origin x, y, z, direction x, y, z], from the camera of the last drawn frame. Stereo
+    // sessions use the first view, normally the left eye; handheld AR has only the one.
+    // Screen taps during a session are reported by `input_poses` and the select callbacks with
+    // their target ray already resolved, so this is for points not tied to an input source.
+    pub fn screen_to_ray(&self, x: f32, y: f32) -> Option<Vec<f32>> {
+        let last_view = self.last_view.borrow();
+        let (view, projection) = last_view.as_ref()?;
+        let (origin, direction) = math::screen_to_ray(view, projection, x, y)?;
+        Some(vec![
+            origin[0],
+            origin[1],
+            origin[2],
+            direction[0],
+            direction[1],
+            direction[2],
+        ])
+    }
+
+    // Aligns `spawn_transform` with the viewer's horizontal position and yaw on the next tracked frame.
+    pub fn set_auto_recenter(&self, spawn_transform: Option<Vec<f32>>) -> Result<(), JsValue> {
+        let spawn = match spawn_transform {
+            Some(m) if m.len() == 16 => Some(math::to_mat4(&m)),
+            Some(_) => return Err(JsValue::from("spawn transform must have 16 elements")),
+            None => None,
+        };
+        self.auto_recenter.replace(spawn);
+        Ok(())
+    }
+
+    // Moves the origin under the viewer and turns it to face where they look, on the next
+    // tracked frame. Only horizontal position and yaw change, so the floor stays level. A
+    // fallback to a simpler reference space recenters again in the new space.
+    pub fn recenter(&self) {
+        self.auto_recenter.replace(Some(math::IDENTITY));
+    }
+
+    // Replaces the built-in shaders used by `start`. Meshes feed the `vertexPosition` and
+    // `vertexColor` attributes, and the `model`, `view` and `projection` matrices and the
+    // `clipEnabled` and `clipPlane` uniforms are set when the program declares them. Returns
+    // the compile or link info log when the sources don't build.
+    pub fn set_shaders(&self, vertex_src: &str, fragment_src: &str) -> Result<(), JsValue> {
+        let program =
+            self.program_cache
+                .borrow_mut()
+                .get_or_link(&self.gl, vertex_src, fragment_src)?;
+        self.program.replace(Some(program));
+        Ok(())
+    }
+
+    // `vertices` holds position (3) and color (3) per vertex; `indices` three per triangle.
+    // Meshes are drawn in both eyes every frame.
+    pub fn add_mesh(&self, vertices: &[f32], indices: &[u16]) -> Result<usize, JsValue> {
+        let mesh = Mesh::new(vertices, indices)?;
+        Ok(self.meshes.borrow_mut().add(mesh))
+    }
+
+    // Adds a mesh from Wavefront OBJ text, using its `v`, `vn` and `f` lines. Meshes have no
+    // normals or materials, so vertices are colored by their normal, or white without one.
+    // `coordinate_convention` is the axes the model was authored in: "y-up" (the default, as in
+    // WebXR), "z-up" for Blender exports or "left-handed" for Y-up, +Z forward engines.
+    pub fn add_mesh_from_obj(
+        &self,
+        obj_text: &str,
+        coordinate_convention: Option<String>,
+    ) -> Result<usize, JsValue> {
+        let convention = match coordinate_convention {
+            Some(name) => name.parse()?,
+            None => CoordinateConvention::YUp,
+        };
+        let (vertices, indices) = obj::parse(obj_text, convention)?;
+        self.add_mesh(&vertices, &indices)
+    }
+
+    // Sets the column-major model matrix of a mesh returned by `add_mesh`. Meshes start with
+    // the identity.
+    pub fn set_mesh_transform(&self, id: usize, matrix: &[f32]) -> Result<(), JsValue> {
+        if matrix.len() != 16 {
+            return Err(JsValue::from("a transform needs 16 floats"));
+        }
+        match self.meshes.borrow_mut().get_mut(id) {
+            Some(mesh) => {
+                mesh.model = math::to_mat4(matrix);
+                Ok(())
+            }
+            None => Err(JsValue::from(format!("no mesh with id {}", id))),
+        }
+    }
+
+    pub fn add_line(
+        &self,
+        start: &[f32],
+        end: &[f32],
+        width: f32,
+        color: &[f32],
+    ) -> Result<usize, JsValue> {
+        if start.len() != 3 || end.len() != 3 || color.len() != 3 {
+            return Err(JsValue::from("start, end and color must have 3 elements"));
+        }
+        let line = Line {
+            start: [start[0], start[1], start[2]],
+            end: [end[0], end[1], end[2]],
+            width,
+            color: [color[0], color[1], color[2]],
+        };
+        Ok(self.lines.borrow_mut().add(line))
+    }
+
+    pub fn clear_lines(&self) {
+        self.lines.borrow_mut().clear();
+    }
+
+    // Line widths are in meters by default, or in pixels when enabled.
+    pub fn set_line_screen_space(&self, enabled: bool) {
+        self.lines.borrow_mut().screen_space = enabled;
+    }
+
+    // Called as callback(handedness, targetRayMatrix, gripMatrix) with poses resolved
+    // against the event's own frame. Matrices are undefined when not tracked. For screen taps
+    // the target ray runs from the tapped point into the scene, ready for hit testing.
+    pub fn set_on_select(&self, callback: Option<js_sys::Function>) {
+        self.on_select.replace(callback);
+    }
+
+    // Registers `callback` for one of the events below, replacing any callback set before,
+    // either here or through the matching `set_on_*` method:
+    //
+    // - "start", "end": session info, see `set_on_session_start`.
+    // - "frame": time, viewer pose and delta time, see `set_on_frame`.
+    // - "select": handedness, target ray and grip, see `set_on_select`.
+    // - "selectstart", "selectend": handedness.
+    // - "inputsourceschange": arrays of added and removed XRInputSources.
+    // - "inputadded", "inputremoved": one XRInputSource per call.
+    // - "visibilitychange": the new visibility state.
+    // - "pinchstart", "pinchend": handedness and position, see `set_on_pinch_start`.
+    // - "trackinglost": the frame time, see `set_on_tracking_lost`.
+    // - "placed": anchor id and pose, see `set_on_placed`.
+    // - "gesture", "qualitychange", "referencespacechange": see the matching `set_on_*`.
+    pub fn on(&self, event: &str, callback: js_sys::Function) -> Result<(), JsValue> {
+        self.event_callback(event)?.replace(Some(callback));
+        Ok(())
+    }
+
+    // Removes the callback registered for `event`.
+    pub fn off(&self, event: &str) -> Result<(), JsValue> {
+        self.event_callback(event)?.replace(None);
+        Ok(())
+    }
+
+    // Called with the handedness of the input source when a select (trigger pull, pinch or
+    // screen tap) begins.
+    pub fn set_on_select_start(&self, callback: Option<js_sys::Function>) {
+        self.on_select_start.replace(callback);
+    }
+
+    // Called with the handedness of the input source when a select ends, whether it completed
+    // or was cancelled.
+    pub fn set_on_select_end(&self, callback: Option<js_sys::Function>) {
+        self.on_select_end.replace(callback);
+    }
+
+    pub fn capabilities(&self) -> Result<JsValue, JsValue> {
+        let capabilities = capabilities::query(self.gl.as_ref());
+        JsValue::from_serde(&capabilities).map_err(|err| JsValue::from(err.to_string()))
+    }
+
+    // Tilts controller and hand target rays downward around their local X axis. Screen taps
+    // aren't tilted.
+    pub fn set_pointer_tilt(&self, degrees: f32) {
+        self.pointer_tilt.replace(degrees.to_radians());
+    }
+
+    // Reports left-handed input sources as right-handed and vice versa, for runtimes that get
+    // them the wrong way round.
+    pub fn set_swap_handedness(&self, swap: bool) {
+        self.swap_handedness.replace(swap);
+    }
+
+    // Discards world-space fragments where dot(normal, position) < distance.
+    pub fn set_clip_plane(
+        &self,
+        normal: &[f32],
+        distance: f32,
+        enabled: bool,
+    ) -> Result<(), JsValue> {
+        if normal.len() != 3 {
+            return Err(JsValue::from("normal must have 3 elements"));
+        }
+        let plane = math::plane(&[normal[0], normal[1], normal[2]], distance);
+        self.clip_plane
+            .replace(if enabled { Some(plane) } else { None });
+        Ok(())
+    }
+
+    // Returns null when the context is lost.
+    pub fn context_attributes(&self) -> JsValue {
+        match self.gl.get_context_attributes() {
+            Some(attributes) => attributes.into(),
+            None => {
+                log!("context attributes unavailable, the WebGL context may be lost");
+                JsValue::NULL
+            }
+        }
+    }
+
+    pub fn input_sources(&self) -> js_sys::Array {
+        self.input_sources.borrow().iter().collect()
+    }
+
+    // One of "direct-touch" (tracked hands), "ray" (controllers), "tap" (handheld AR screen
+    // input) or "gaze", for the input sources currently connected.
+    pub fn recommended_interaction(&self) -> String {
+        let sources: Vec<InputDescriptor> = self
+            .input_sources
+            .borrow()
+            .iter()
+            .map(InputDescriptor::from_source)
+            .collect();
+        String::from(interaction::recommend(&sources).as_str())
+    }
+
+    // Array of `{ handedness, hand, targetRayMode, targetRay, grip }` for the input sources tracked in the
+    // last frame, with column-major matrices in the reference space. `hand` is true for
+    // tracked hands. `grip` is left out for sources without a grip space, such as screen and
+    // gaze input.
+    pub fn input_poses(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&*self.input_poses.borrow())
+            .map_err(|err| JsValue::from(err.to_string()))
+    }
+
+    // Background color, RGBA in [0, 1]. Defaults to transparent in AR sessions, so the
+    // passthrough shows, and opaque black otherwise.
+    pub fn set_clear_color(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.custom_clear_color.replace(Some([
+            r.clamp(0., 1.),
+            g.clamp(0., 1.),
+            b.clamp(0., 1.),
+            a.clamp(0., 1.),
+        ]));
+    }
+
+    // Draws meshes for both eyes in a single pass, instead of once per eye. Antialiased layers
+    // need OCULUS_multiview; OVR_multiview2 is enough for layers without antialiasing. Falls
+    // back to drawing per eye without a suitable extension, with custom shaders, with plane or
+    // depth occlusion on, and for sessions that don't have exactly two views. The extension is
+    // looked for when a session starts, so enable this before `start`.
+    pub fn set_multiview(&self, enabled: bool) {
+        self.multiview.replace(enabled);
+    }
+
+    // Writes the planes the runtime detects (floors, walls, tables) into the depth buffer
+    // before drawing meshes, without color, so virtual content behind real surfaces is hidden
+    // while the camera image shows through. Needs an immersive-ar session with the
+    // "plane-detection" feature, and depth testing with a depth buffer.
+    pub fn set_plane_occlusion(&self, enabled: bool) {
+        self.plane_occlusion.replace(enabled);
+    }
+
+    // Hides mesh fragments behind real-world surfaces measured by the runtime's depth sensor,
+    // such as hands and furniture. Needs an immersive-ar session with the "depth-sensing"
+    // feature, and the default shaders. Views the runtime has no depth for in a frame are
+    // drawn without occlusion. Like plane occlusion, this turns multiview off.
+    pub fn set_depth_occlusion(&self, enabled: bool) {
+        self.depth_occlusion.replace(enabled);
+    }
+
+    // Depth testing is on by default; with it off, meshes are drawn in the order added.
+    pub fn set_depth_test(&self, enabled: bool) {
+        self.depth_test.replace(enabled);
+    }
+
+    // One of the WebGL comparison constants, e.g. `gl.LEQUAL`. Defaults to `gl.LESS`.
+    pub fn set_depth_func(&self, func: u32) -> Result<(), JsValue> {
+        match func {
+            WebGl2RenderingContext::NEVER
+            | WebGl2RenderingContext::LESS
+            | WebGl2RenderingContext::EQUAL
+            | WebGl2RenderingContext::LEQUAL
+            | WebGl2RenderingContext::GREATER
+            | WebGl2RenderingContext::NOTEQUAL
+            | WebGl2RenderingContext::GEQUAL
+            | WebGl2RenderingContext::ALWAYS => {
+                self.depth_func.replace(func);
+                Ok(())
+            }
+            _ => Err(JsValue::from(format!(
+                "unknown depth function: {:#x}",
+                func
+            ))),
+        }
+    }
+
+    // Array of `{ handedness, joints }` for each tracked hand in the last frame. `joints` has
+    // the 25 WebXR hand joints in XRHandJoint order, each `{ matrix, radius }` or null when
+    // not tracked. Needs the "hand-tracking" feature; controllers are not included.
+    pub fn hand_poses(&self) -> Result<JsValue, JsValue> {
+        JsValue::from_serde(&*self.hand_poses.borrow())
+            .map_err(|err| JsValue::from(err.to_string()))
+    }
+
+    // Called as callback(handedness, position) when the thumb and index finger tips of a
+    // tracked hand come together, with `position` the [x, y, z] point between them in the
+    // reference space. Needs the "hand-tracking" feature.
+    pub fn set_on_pinch_start(&self, callback: Option<js_sys::Function>) {
+        self.on_pinch_start.replace(callback);
+    }
+
+    // Called like the pinch start callback when the tips separate again, or when the hand
+    // stops being tracked mid-pinch.
+    pub fn set_on_pinch_end(&self, callback: Option<js_sys::Function>) {
+        self.on_pinch_end.replace(callback);
+    }
+
+    // Distances in meters between the thumb and index tips: a pinch starts below
+    // `start_distance` and ends above `end_distance`, which must not be smaller. Defaults to
+    // 0.015 and 0.03; raise them if pinches are missed, lower them if they fire by accident.
+    pub fn set_pinch_sensitivity(
+        &self,
+        start_distance: f32,
+        end_distance: f32,
+    ) -> Result<(), JsValue> {
+        if start_distance <= 0. || start_distance.is_nan() || end_distance < start_distance {
+            return Err(JsValue::from(
+                "pinch distances must be positive, with the end distance at least the start",
+            ));
+        }
+        let mut pinches = self.pinches.borrow_mut();
+        pinches.start_distance = start_distance;
+        pinches.end_distance = end_distance;
+        Ok(())
+    }
+
+    // Draws a debug ray from each tracked input source along its target ray.
+    pub fn set_controller_rays(&self, enabled: bool) {
+        self.controller_rays.replace(enabled);
+    }
+
+    // Previews where screen taps point: a ray and reticle along the tap's target ray while it
+    // is held, fading out over a moment after release.
+    pub fn set_tap_feedback(&self, enabled: bool) {
+        let mut tap_feedback = self.tap_feedback.borrow_mut();
+        tap_feedback.enabled = enabled;
+        if !enabled {
+            tap_feedback.reset();
+        }
+    }
+
+    // Called with the XRInputSource that was connected.
+    pub fn set_on_input_added(&self, callback: Option<js_sys::Function>) {
+        self.on_input_added.replace(callback);
+    }
+
+    // Called with the XRInputSource that was disconnected.
+    pub fn set_on_input_removed(&self, callback: Option<js_sys::Function>) {
+        self.on_input_removed.replace(callback);
+    }
+
+    // 0 waits for the session request indefinitely.
+    pub fn set_session_timeout(&self, timeout_ms: i32) {
+        self.session_timeout_ms.replace(timeout_ms);
+    }
+
+    pub fn is_known_feature(name: &str) -> bool {
+        features::is_known_feature(name)
+    }
+
+    // Estimated bytes of buffer and texture data uploaded to the GPU.
+    pub fn estimated_gpu_memory(&self) -> u64 {
+        self.gpu_memory.borrow().total()
+    }
+
+    // Logs a warning whenever an upload pushes the estimate past `bytes`.
+    pub fn set_gpu_memory_budget(&self, bytes: Option<u64>) {
+        self.gpu_memory.borrow_mut().budget = bytes;
+    }
+
+    // While select is held, re-fires the select callback after `delay_ms` and then every
+    // `interval_ms`. An interval of 0 or less disables repeating.
+    pub fn set_select_repeat(&self, delay_ms: f64, interval_ms: f64) {
+        self.select_repeat.replace(if interval_ms > 0. {
+            Some(SelectRepeat::new(delay_ms, interval_ms))
+        } else {
+            None
+        });
+    }
+
+    // Timestamp of the current XR frame in milliseconds, on the same time base as
+    // `performance.now()`. 0 before the first frame.
+    pub fn frame_time(&self) -> f64 {
+        self.frame_timing.borrow().time
+    }
+
+    // The runtime's predicted display time for the current frame, or undefined if it isn't
+    // reported.
+    pub fn predicted_display_time(&self) -> Option<f64> {
+        self.frame_timing.borrow().predicted_display_time
+    }
+
+    // Milliseconds between the frame timestamp and the predicted display time.
+    pub fn frame_time_drift(&self) -> f64 {
+        self.frame_timing.borrow().drift()
+    }
+
+    // Called every frame before drawing with the frame time, the viewer pose matrix
+    // (column-major) or null while tracking is lost, and the seconds since the previous frame
+    // (0 on the first one). Nothing is borrowed during the call, so the callback can use the
+    // other XrApp methods.
+    pub fn set_on_frame(&self, callback: Option<js_sys::Function>) {
+        self.on_frame.replace(callback);
+    }
+
+    // The viewer's transform in the reference space as a column-major 4x4 matrix, updated every
+    // frame. Undefined before the first frame and while tracking is lost.
+    pub fn last_viewer_pose(&self) -> Option<Vec<f32>> {
+        self.viewer_pose.borrow().map(|pose| pose.to_vec())
+    }
+
+    // Viewer velocity estimated from the last two frames: linear in m/s followed by angular
+    // (axis scaled by rad/s), both in reference space. Zero while tracking is lost.
+    pub fn head_velocity(&self) -> Vec<f32> {
+        let velocity = self.head_velocity.borrow();
+        let mut out = velocity.linear.to_vec();
+        out.extend_from_slice(&velocity.angular);
+        out
+    }
+
+    // Called with "nod" or "shake" when the viewer nods or shakes their head.
+    pub fn set_on_gesture(&self, callback: Option<js_sys::Function>) {
+        self.on_gesture.replace(callback);
+    }
+
+    // `threshold` is the head rotation speed in degrees per second each swing has to exceed;
+    // `cooldown_ms` suppresses further gestures for that long after one fires.
+    pub fn set_gesture_sensitivity(&self, threshold: f32, cooldown_ms: f64) {
+        let mut gestures = self.gestures.borrow_mut();
+        gestures.threshold = threshold.to_radians();
+        gestures.cooldown_ms = cooldown_ms;
+    }
+
+    // Called once on the first rendered frame with the session's mode, reference space,
+    // enabled features and input profiles.
+    pub fn set_on_session_start(&self, callback: Option<js_sys::Function>) {
+        self.on_session_start.replace(callback);
+    }
+
+    // Called when the session ends with the same info plus its duration and frame count.
+    pub fn set_on_session_end(&self, callback: Option<js_sys::Function>) {
+        self.on_session_end.replace(callback);
+    }
+
+    // Draws each eye's viewport border and projection center crosshair on top of the scene,
+    // red for the left eye and blue for the right, to check for swapped eyes or wrong projections.
+    pub fn set_stereo_debug(&self, enabled: bool) {
+        self.stereo_debug.replace(enabled);
+    }
+
+    // When the viewer pose has been unavailable for `threshold_ms`, re-requests the reference
+    // space as the next simpler type (bounded-floor, local-floor, local, then viewer). A
+    // threshold of 0 or less turns this off.
+    pub fn set_tracking_loss_fallback(&self, threshold_ms: f64) {
+        self.tracking_loss.replace(if threshold_ms > 0. {
+            Some(TrackingLoss::new(threshold_ms))
+        } else {
+            None
+        });
+    }
+
+    // Called with the frame time when the viewer pose goes missing, once per loss. Frames aren't
+    // drawn until tracking comes back.
+    pub fn set_on_tracking_lost(&self, callback: Option<js_sys::Function>) {
+        self.on_tracking_lost.replace(callback);
+    }
+
+    // Reference space type in use, e.g. "local" after falling back from "bounded-floor".
+    // Floor-relative types put y = 0 on the floor, others at the viewer's starting height.
+    // Before a session starts it's "bounded-floor".
+    pub fn reference_space_type(&self) -> String {
+        JsValue::from(*self.ref_space_type.borrow())
+            .as_string()
+            .unwrap_or_default()
+    }
+
+    // Called with the new reference space type after falling back from tracking loss.
+    pub fn set_on_reference_space_change(&self, callback: Option<js_sys::Function>) {
+        self.on_reference_space_change.replace(callback);
+    }
+
+    // `mode` is "immersive-vr" (the default), "immersive-ar" or "inline". AR sessions clear to
+    // transparent so the camera passthrough shows through. Dragging on the canvas of an inline
+    // session looks around.
+    // `reference_space` defaults to bounded-floor for VR, local-floor for AR and viewer for
+    // inline. If the runtime rejects it, local-floor, local and then viewer are tried in turn.
+    // `required_features` and `optional_features` are arrays of feature names such as
+    // "local-floor" or "hand-tracking". The session request rejects if a required feature is
+    // unsupported; optional ones are enabled when available.
+    // Arguments left undefined fall back to the `XrAppConfig` given to `with_config`.
+    // `framebuffer_scale` overrides the quality presets' scale for this session, relative to the
+    // default framebuffer size. Values below 1 render fewer pixels, trading sharpness for
+    // performance.
+    // `layer_options` picks antialiasing and the depth and stencil buffers of the XR layer.
+    pub fn init(
+        &self,
+        mode: Option<String>,
+        reference_space: Option<String>,
+        required_features: Option<js_sys::Array>,
+        optional_features: Option<js_sys::Array>,
+        framebuffer_scale: Option<f64>,
+        layer_options: Option<LayerOptions>,
+    ) -> Promise {
+        let config = &self.config;
+        let mode = mode.or_else(|| config.mode.clone());
+        let reference_space = reference_space.or_else(|| config.reference_space.clone());
+        let required_features = required_features.or_else(|| config.required_features.clone());
+        let optional_features = optional_features.or_else(|| config.optional_features.clone());
+        let framebuffer_scale = framebuffer_scale.or(config.framebuffer_scale);
+        let layer_options = layer_options.or(config.layer_options);
+        let mode = match mode.as_deref().unwrap_or("immersive-vr").parse() {
+            Ok(mode) => mode,
+            Err(err) => return Promise::reject(&JsValue::from(err)),
+        };
+        let required_features = match features::feature_names(required_features.as_ref()) {
+            Ok(features) => features,
+            Err(err) => return Promise::reject(&JsValue::from(err)),
+        };
+        let requested_features = match features::feature_names(optional_features.as_ref()) {
+            Ok(features) => features,
+            Err(err) => return Promise::reject(&JsValue::from(err)),
+        };
+        if let Some(scale) = framebuffer_scale {
+            if scale <= 0. || scale.is_nan() {
+                return Promise::reject(&JsValue::from(format!(
+                    "framebuffer scale must be positive: {}",
+                    scale
+                )));
+            }
+        }
+        self.framebuffer_scale.replace(framebuffer_scale);
+        if let Some(layer_options) = layer_options {
+            self.layer_options.replace(layer_options);
+        }
+        let ref_space_type = match reference_space {
+            Some(name) => match XrReferenceSpaceType::from_js_value(&JsValue::from(&name)) {
+                Some(ref_space_type) => ref_space_type,
+                None => {
+                    return Promise::reject(&JsValue::from(format!(
+                        "unknown reference space type: {}",
+                        name
+                    )))
+                }
+            },
+            None => match mode {
+                SessionMode::ImmersiveVr => XrReferenceSpaceType::BoundedFloor,
+                SessionMode::ImmersiveAr => XrReferenceSpaceType::LocalFloor,
+                SessionMode::Inline => XrReferenceSpaceType::Viewer,
+            },
+        };
+        if mode == SessionMode::Inline {
+            if let Err(err) = self.listen_for_drag_look() {
+                return Promise::reject(&err);
+            }
+        }
+
+        // Everything but viewer has to be requested as a feature, including the fallbacks.
+        let mut optional_features = vec![JsValue::from(ref_space_type)
+            .as_string()
+            .unwrap_or_default()];
+        if mode != SessionMode::Inline {
+            optional_features.push(String::from("local-floor"));
+        }
+        optional_features.extend(requested_features);
+        optional_features
+            .retain(|feature| feature != "viewer" && !required_features.contains(feature));
+        optional_features.sort();
+        optional_features.dedup();
+        self.request_session(mode, ref_space_type, &required_features, &optional_features)
+    }
+
+    // Starts an inline session that renders into the page canvas without a headset, like
+    // `init("inline")`, with the rest taken from the config and the viewer reference space by
+    // default. Create the app with `with_canvas` so the output is visible; the canvas is
+    // resized to its displayed size every frame.
+    // Dragging on the canvas looks around. Unlike immersive sessions, inline sessions don't need
+    // a user gesture and run their frame loop without a headset.
+    pub fn init_inline(&self) -> Promise {
+        self.init(
+            Some(String::from(SessionMode::Inline.as_str())),
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    pub fn start(&self) {
+        let session: &Option<XrSession> = &self.session.borrow();
+        let sess: &XrSession = if let Some(sess) = session {
+            sess
+        } else {
+            return ();
+        };
+
+        let f = Rc::new(RefCell::new(None));
+        let g = f.clone();
+        let running = self.running.clone();
+        let frame_handle = self.frame_handle.clone();
+        let viewer_pose = self.viewer_pose.clone();
+        let on_frame = self.on_frame.clone();
+        let input_poses = self.input_poses.clone();
+        let controller_rays = self.controller_rays.clone();
+        let visible = self.visible.clone();
+        let depth_test = self.depth_test.clone();
+        let layer_options = self.layer_options.clone();
+        let framebuffer_scale = self.framebuffer_scale.clone();
+        let on_tracking_lost = self.on_tracking_lost.clone();
+        let multiview = self.multiview.clone();
+        let blending = self.blending.clone();
+        let mut tracked = true;
+        let mut last_time = None;
+        let depth_func = self.depth_func.clone();
+        let hit_test_source = self.hit_test_source.clone();
+        let hit_pose = self.hit_pose.clone();
+        let anchors = self.anchors.clone();
+        let hand_poses = self.hand_poses.clone();
+        let custom_clear_color = self.custom_clear_color.clone();
+        let drag_look = self.drag_look.clone();
+        let meshes = self.meshes.clone();
+
+        let gl = self.gl.clone();
+        let ref_space = self.ref_space.clone();
+        let last_view = self.last_view.clone();
+        let auto_recenter = self.auto_recenter.clone();
+        let lines = self.lines.clone();
+        let gpu_memory = self.gpu_memory.clone();
+        let on_select = self.on_select.clone();
+        let pointer_tilt = self.pointer_tilt.clone();
+        let swap_handedness = self.swap_handedness.clone();
+        let select_repeat = self.select_repeat.clone();
+        let frame_timing = self.frame_timing.clone();
+        let head_velocity = self.head_velocity.clone();
+        let gestures = self.gestures.clone();
+        let on_gesture = self.on_gesture.clone();
+        let session_info = self.session_info.clone();
+        let on_session_start = self.on_session_start.clone();
+        let input_sources = self.input_sources.clone();
+        let clip_plane = self.clip_plane.clone();
+        let session_mode = self.session_mode.clone();
+        let quality = self.quality.clone();
+        let auto_quality = self.auto_quality.clone();
+        let on_quality_change = self.on_quality_change.clone();
+        let stereo_debug = self.stereo_debug.clone();
+        let ref_space_type = self.ref_space_type.clone();
+        let tracking_loss = self.tracking_loss.clone();
+        let on_reference_space_change = self.on_reference_space_change.clone();
+        let eye_scale = self.eye_scale.clone();
+        let premultiplied_alpha = self.premultiplied_alpha.clone();
+        let mirror_horizontal = self.mirror_horizontal.clone();
+        let viewport_inset = self.viewport_inset.clone();
+        let uniforms = self.uniforms.clone();
+        let pinches = self.pinches.clone();
+        let on_pinch_start = self.on_pinch_start.clone();
+        let on_pinch_end = self.on_pinch_end.clone();
+        let plane_occlusion = self.plane_occlusion.clone();
+        let tap_feedback = self.tap_feedback.clone();
+        let depth_occlusion = self.depth_occlusion.clone();
+        let placement = self.placement.clone();
+        let on_placed = self.on_placed.clone();
+        let renderers = self.renderers.clone();
+        let current_session = self.session.clone();
+        let recenter_spawn = self.recenter_spawn.clone();
+
+        let custom_program = self.program.borrow().clone();
+        let shader_profram = match custom_program.clone() {
+            Some(program) => program,
+            None => match self.program_cache.borrow_mut().get_or_link(
+                &gl,
+                VERTEX_SHADER,
+                FRAGMENT_SHADER,
+            ) {
+                Ok(program) => program,
+                Err(err) => {
+                    log!("{}", err);
+                    return;
+                }
+            },
+        };
+
+        gl.enable(WebGl2RenderingContext::CULL_FACE);
+        gl.use_program(Some(&shader_profram));
+        // The program may be new or relinked since the last start, with none of the cached
+        // values uploaded to it.
+        {
+            let mut uniforms = self.uniforms.borrow_mut();
+            uniforms.invalidate();
+            uniforms.uploads = 0;
+            uniforms.skipped = 0;
+        }
+
+        let view_location = gl.get_uniform_location(&shader_profram, "view");
+        let projection_location = gl.get_uniform_location(&shader_profram, "projection");
+        let clip_enabled_location = gl.get_uniform_location(&shader_profram, "clipEnabled");
+        let clip_plane_location = gl.get_uniform_location(&shader_profram, "clipPlane");
+        let depth_enabled_location = gl.get_uniform_location(&shader_profram, "depthEnabled");
+        let depth_uv_transform_location =
+            gl.get_uniform_location(&shader_profram, "depthUvTransform");
+        let depth_texel_weights_location =
+            gl.get_uniform_location(&shader_profram, "depthTexelWeights");
+
+        // Starting again without `end` replaces the previous session's objects.
+        self.delete_renderers();
+        let mesh_renderer = MeshRenderer::new(&gl, &shader_profram);
+        // The multiview shader is a variant of the default one, so custom shaders draw per eye.
+        let multiview_renderer = if custom_program.is_some() || !*self.multiview.borrow() {
+            None
+        } else {
+            let antialias = sess
+                .render_state()
+                .base_layer()
+                .is_some_and(|layer| layer.antialias());
+            match Multiview::new(
+                &gl,
+                &mut self.program_cache.borrow_mut(),
+                &mesh_renderer,
+                FRAGMENT_SHADER,
+                antialias,
+            ) {
+                Ok(Some(multiview_renderer)) => Some(multiview_renderer),
+                Ok(None) => {
+                    log!(
+                        "multiview needs OCULUS_multiview, or OVR_multiview2 without antialiasing; drawing each view separately"
+                    );
+                    None
+                }
+                Err(err) => {
+                    log!("multiview error:{}", err);
+                    None
+                }
+            }
+        };
+
+        let line_renderer = match LineRenderer::new(&gl, &mut self.program_cache.borrow_mut()) {
+            Ok(line_renderer) => Some(line_renderer),
+            Err(err) => {
+                log!("line renderer error:{}", err);
+                None
+            }
+        };
+        let mut ray_lines = Lines::default();
+        let ray_renderer = match LineRenderer::new(&gl, &mut self.program_cache.borrow_mut()) {
+            Ok(ray_renderer) => Some(ray_renderer),
+            Err(err) => {
+                log!("controller ray renderer error:{}", err);
+                None
+            }
+        };
+        let mut tap_lines = Lines::default();
+        let mut tap_drawn = false;
+        let tap_renderer = match LineRenderer::new(&gl, &mut self.program_cache.borrow_mut()) {
+            Ok(tap_renderer) => Some(tap_renderer),
+            Err(err) => {
+                log!("tap feedback renderer error:{}", err);
+                None
+            }
+        };
+        let plane_occluder = match PlaneOccluder::new(&gl, &mut self.program_cache.borrow_mut()) {
+            Ok(plane_occluder) => Some(plane_occluder),
+            Err(err) => {
+                log!("plane occluder error:{}", err);
+                None
+            }
+        };
+        let stereo_debug_renderer = match StereoDebug::new(
+            &gl,
+            &mut self.program_cache.borrow_mut(),
+            &mut self.gpu_memory.borrow_mut(),
+        ) {
+            Ok(stereo_debug_renderer) => Some(stereo_debug_renderer),
+            Err(err) => {
+                log!("stereo debug error:{}", err);
+                None
+            }
+        };
+        self.renderers.replace(Some(Renderers {
+            mesh: mesh_renderer,
+            multiview: multiview_renderer,
+            line: line_renderer,
+            ray: ray_renderer,
+            tap: tap_renderer,
+            plane_occluder,
+            stereo_debug: stereo_debug_renderer,
+            depth: DepthOcclusion::default(),
+        }));
+
+        *g.borrow_mut() = Some(Closure::wrap(Box::new(move |time: f64, frame: XrFrame| {
+            // A frame that was already queued when `end` ran must not reschedule the loop.
+            if !*running.borrow() {
+                return;
+            }
+            frame_timing.replace(FrameTiming::from_frame(time, &frame));
+            let started = session_info.borrow_mut().as_mut().and_then(|info| {
+                if info.frame(time, &input_sources.borrow()) {
+                    Some(info.clone())
+                } else {
+                    None
+                }
+            });
+            if let Some(info) = started {
+                analytics::call_session_callback(&on_session_start.borrow().clone(), &info);
+            }
+            let sess: XrSession = frame.session();
+
+            let current = quality.borrow().preset;
+            let downgrade = auto_quality
+                .borrow_mut()
+                .as_mut()
+                .and_then(|auto_quality| auto_quality.update(time, current));
+            if let Some(preset) = downgrade {
+                let settings = QualitySettings::from_preset(preset);
+                quality.replace(settings);
+                let scale = *framebuffer_scale.borrow();
+                match apply_xr_layer(&sess, &gl, &settings, &layer_options.borrow(), scale) {
+                    Ok(layer) => size_canvas_to_layer(&gl, &layer),
+                    Err(err) => {
+                        log!("auto quality error:{:?}", err);
+                    }
+                }
+                let callback = on_quality_change.borrow().clone();
+                if let Some(callback) = callback {
+                    if let Err(err) = callback.call1(&JsValue::NULL, &preset.into()) {
+                        log!("quality change callback error:{:?}", err);
+                    }
+                }
+            }
+
+            let gl_layer = match sess.render_state().base_layer() {
+                Some(gl_layer) => gl_layer,
+                None => {
+                    request_next_frame(&sess, &f, &running, &frame_handle);
+                    return;
+                }
+            };
+            let current_session_mode = *session_mode.borrow();
+            if current_session_mode == SessionMode::Inline {
+                fit_canvas_to_display(&gl);
+            }
+
+            gl.bind_framebuffer(
+                WebGl2RenderingContext::FRAMEBUFFER,
+                Some(&gl_layer.framebuffer()),
+            );
+            let alpha_mode = match *premultiplied_alpha.borrow() {
+                Some(true) => AlphaMode::Premultiplied,
+                Some(false) => AlphaMode::Straight,
+                None => AlphaMode::for_session(&sess),
+            };
+            let (blend, src_factor, dst_factor) = blending.borrow().unwrap_or_else(|| {
+                let (src_factor, dst_factor) = alpha_mode.blend_func();
+                (true, src_factor, dst_factor)
+            });
+            if blend {
+                gl.enable(WebGl2RenderingContext::BLEND);
+            } else {
+                gl.disable(WebGl2RenderingContext::BLEND);
+            }
+            gl.blend_func(src_factor, dst_factor);
+            if *depth_test.borrow() {
+                gl.enable(WebGl2RenderingContext::DEPTH_TEST);
+            } else {
+                gl.disable(WebGl2RenderingContext::DEPTH_TEST);
+            }
+            gl.depth_func(*depth_func.borrow());
+            let background = clear_color(current_session_mode, *custom_clear_color.borrow());
+            let [r, g, b, a] = background;
+            gl.clear_color(r, g, b, a);
+            let mut clear_mask = WebGl2RenderingContext::COLOR_BUFFER_BIT;
+            let layer_options = *layer_options.borrow();
+            if layer_options.depth() {
+                gl.clear_depth(1.);
+                clear_mask |= WebGl2RenderingContext::DEPTH_BUFFER_BIT;
+            }
+            if layer_options.stencil() {
+                gl.clear_stencil(0);
+                clear_mask |= WebGl2RenderingContext::STENCIL_BUFFER_BIT;
+            }
+            gl.clear(clear_mask);
+
+            // Cloned rather than borrowed, so the frame callback can replace the reference space.
+            // It is only missing while a session is being set up or torn down.
+            let current_ref_space = ref_space.borrow().clone();
+            let mut current_ref_space = match current_ref_space {
+                Some(current_ref_space) => current_ref_space,
+                None => {
+                    request_next_frame(&sess, &f, &running, &frame_handle);
+                    return;
+                }
+            };
+            let spawn = *auto_recenter.borrow();
+            if let Some(spawn) = spawn {
+                let offset_space = recenter_reference_space(&frame, &current_ref_space, &spawn);
+                if let Some(offset_space) = offset_space {
+                    ref_space.replace(Some(offset_space.clone()));
+                    current_ref_space = offset_space;
+                    auto_recenter.replace(None);
+                    recenter_spawn.replace(Some(spawn));
+                    reset_head_motion(&head_velocity, &gestures);
+                }
+            }
+
+            let ref_pose = if current_session_mode == SessionMode::Inline {
+                drag_look.borrow().apply(&current_ref_space)
+            } else {
+                current_ref_space
+            };
+            let repeats = select_repeat
+                .borrow_mut()
+                .as_mut()
+                .map_or_else(Vec::new, |repeat| repeat.update(time));
+            for source in repeats {
+                call_select_callback(
+                    &on_select,
+                    &frame,
+                    &source,
+                    &ref_pose,
+                    *pointer_tilt.borrow(),
+                    *swap_handedness.borrow(),
+                );
+            }
+            let pose = frame.get_viewer_pose(&ref_pose);
+            let tracked_inputs = input_sources
+                .borrow()
+                .iter()
+                .filter_map(|source| {
+                    InputPose::new(
+                        &frame,
+                        source,
+                        &ref_pose,
+                        handedness(source, *swap_handedness.borrow()),
+                        *pointer_tilt.borrow(),
+                    )
+                })
+                .collect();
+            input_poses.replace(tracked_inputs);
+            let screen_ray = input_poses
+                .borrow()
+                .iter()
+                .find(|input_pose| input_pose.target_ray_mode == "screen")
+                .map(|input_pose| math::to_mat4(&input_pose.target_ray));
+            tap_feedback.borrow_mut().update(screen_ray);
+            let tracked_hands = input_sources
+                .borrow()
+                .iter()
+                .filter_map(|source| {
+                    HandPose::new(
+                        &frame,
+                        source,
+                        &ref_pose,
+                        handedness(source, *swap_handedness.borrow()),
+                    )
+                })
+                .collect();
+            hand_poses.replace(tracked_hands);
+            let pinch_events = pinches.borrow_mut().update(&hand_poses.borrow());
+            for (handedness, event) in pinch_events {
+                if let PinchEvent::Start(_) = event {
+                    confirm_placement(&placement, &hit_pose, &anchors, &on_placed);
+                }
+                let (callback, position) = match event {
+                    PinchEvent::Start(position) => (on_pinch_start.borrow().clone(), position),
+                    PinchEvent::End(position) => (on_pinch_end.borrow().clone(), position),
+                };
+                if let Some(callback) = callback {
+                    let position = js_sys::Float32Array::from(&position[..]);
+                    if let Err(err) = callback.call2(&JsValue::NULL, &handedness.into(), &position)
+                    {
+                        log!("pinch callback error:{:?}", err);
+                    }
+                }
+            }
+            let hit = hit_test_source
+                .borrow()
+                .as_ref()
+                .and_then(|source| hit_test::first_hit(&frame, source, &ref_pose));
+            hit_pose.replace(hit);
+            let creations = anchors.borrow_mut().update(&frame, &ref_pose);
+            for creation in creations {
+                anchors::complete(
+                    anchors.clone(),
+                    creation,
+                    current_session.clone(),
+                    running.clone(),
+                );
+            }
+            if let Some(placement) = placement.borrow().as_ref() {
+                let model = placement.model(hit, |anchor| anchors.borrow().pose(anchor));
+                if let Some(mesh) = meshes.borrow_mut().get_mut(placement.mesh) {
+                    mesh.model = model;
+                }
+            }
+            let head = pose
+                .as_ref()
+                .map(|pose| math::to_mat4(&pose.transform().matrix()));
+            head_velocity.borrow_mut().update(head.as_ref(), time);
+            viewer_pose.replace(head);
+            let gesture = if let Some(head) = head.as_ref() {
+                let angular = head_velocity.borrow().angular;
+                gestures.borrow_mut().update(head, &angular, time)
+            } else {
+                gestures.borrow_mut().reset();
+                None
+            };
+            let callback = on_gesture.borrow().clone();
+            if let (Some(gesture), Some(callback)) = (gesture, callback) {
+                if let Err(err) = callback.call1(&JsValue::NULL, &gesture.as_str().into()) {
+                    log!("gesture callback error:{:?}", err);
+                }
+            }
+            let callback = on_frame.borrow().clone();
+            let previous_time = last_time.replace(time);
+            if let Some(callback) = callback {
+                let pose = match head.as_ref() {
+                    Some(head) => js_sys::Float32Array::from(&head[..]).into(),
+                    None => JsValue::NULL,
+                };
+                let dt = previous_time.map_or(0., |previous_time| (time - previous_time) / 1000.);
+                if let Err(err) = callback.call3(&JsValue::NULL, &time.into(), &pose, &dt.into()) {
+                    log!("frame callback error:{:?}", err);
+                }
+            }
+            let pose = if let Some(pose) = pose {
+                if let Some(tracking_loss) = tracking_loss.borrow_mut().as_mut() {
+                    tracking_loss.tracked();
+                }
+                tracked = true;
+                pose
+            } else {
+                if tracked {
+                    tracked = false;
+                    log!("viewer pose lost, skipping frames until tracking resumes");
+                    let callback = on_tracking_lost.borrow().clone();
+                    if let Some(callback) = callback {
+                        if let Err(err) = callback.call1(&JsValue::NULL, &time.into()) {
+                            log!("tracking lost callback error:{:?}", err);
+                        }
+                    }
+                }
+                let fall_back = tracking_loss
+                    .borrow_mut()
+                    .as_mut()
+                    .map(|tracking_loss| tracking_loss.lost(time))
+                    == Some(true);
+                if fall_back {
+                    let (head_velocity, gestures) = (head_velocity.clone(), gestures.clone());
+                    let (auto_recenter, recenter_spawn) =
+                        (auto_recenter.clone(), recenter_spawn.clone());
+                    fall_back_reference_space(
+                        &sess,
+                        ref_space.clone(),
+                        ref_space_type.clone(),
+                        tracking_loss.clone(),
+                        on_reference_space_change.clone(),
+                        move || {
+                            reset_head_motion(&head_velocity, &gestures);
+                            // The recenter offset was relative to the old space's origin, so the
+                            // same spawn is aligned again in the new one.
+                            if auto_recenter.borrow().is_none() {
+                                auto_recenter.replace(*recenter_spawn.borrow());
+                            }
+                        },
+                    );
+                }
+                request_next_frame(&sess, &f, &running, &frame_handle);
+                return;
+            };
+            // Hidden behind a system menu or backgrounded: nothing drawn is shown, but frames
+            // keep coming so rendering resumes as soon as the session is visible again.
+            if !*visible.borrow() {
+                request_next_frame(&sess, &f, &running, &frame_handle);
+                return;
+            }
+            let views = pose.views();
+            // Borrowed only after the user callbacks above, which may call `end`.
+            let mut renderers = renderers.borrow_mut();
+            let Renderers {
+                mesh: mesh_renderer,
+                multiview: multiview_renderer,
+                line: line_renderer,
+                ray: ray_renderer,
+                tap: tap_renderer,
+                plane_occluder,
+                stereo_debug: stereo_debug_renderer,
+                depth: depth_textures,
+            } = match renderers.as_mut() {
+                Some(renderers) => renderers,
+                None => {
+                    request_next_frame(&sess, &f, &running, &frame_handle);
+                    return;
+                }
+            };
+            if let Err(err) =
+                mesh_renderer.sync(&gl, &meshes.borrow(), &mut gpu_memory.borrow_mut())
+            {
+                log!("mesh upload error:{}", err);
+            }
+            if let Some(line_renderer) = line_renderer.as_mut() {
+                line_renderer.sync(&gl, &mut lines.borrow_mut(), &mut gpu_memory.borrow_mut());
+            }
+            let occlude_planes = *plane_occlusion.borrow();
+            if let Some(plane_occluder) = plane_occluder.as_mut().filter(|_| occlude_planes) {
+                let vertices = planes::detected_occluders(&frame, &ref_pose);
+                plane_occluder.sync(&gl, &vertices, &mut gpu_memory.borrow_mut());
+            }
+            let draw_rays = *controller_rays.borrow();
+            if let Some(ray_renderer) = ray_renderer.as_mut().filter(|_| draw_rays) {
+                ray_lines.clear();
+                for input_pose in input_poses.borrow().iter() {
+                    ray_lines.add(input_pose.ray());
+                }
+                ray_renderer.sync(&gl, &mut ray_lines, &mut gpu_memory.borrow_mut());
+            }
+            let tap = tap_feedback.borrow_mut().lines(time);
+            // The buffer is only touched while a tap is shown, and once more to empty it.
+            if let Some(tap_renderer) = tap_renderer
+                .as_mut()
+                .filter(|_| tap_drawn || !tap.is_empty())
+            {
+                tap_drawn = !tap.is_empty();
+                tap_lines.clear();
+                for line in tap {
+                    tap_lines.add(line);
+                }
+                tap_renderer.sync(&gl, &mut tap_lines, &mut gpu_memory.borrow_mut());
+            }
+            let mut uniforms = uniforms.borrow_mut();
+            let plane = *clip_plane.borrow();
+            uniforms.uniform1i(
+                &gl,
+                "clipEnabled",
+                clip_enabled_location.as_ref(),
+                plane.is_some() as i32,
+            );
+            if let Some(plane) = plane {
+                uniforms.uniform4f(&gl, "clipPlane", clip_plane_location.as_ref(), &plane);
+            }
+            let mirror = *mirror_horizontal.borrow();
+            let occlude_depth = *depth_occlusion.borrow();
+            gl.front_face(if mirror {
+                WebGl2RenderingContext::CW
+            } else {
+                WebGl2RenderingContext::CCW
+            });
+            let mut cameras = Vec::new();
+            for i in 0..views.length() {
+                let view: XrView = views.get(i).into();
+                quality::request_viewport_scale(&view, eye_scale.borrow().for_eye(view.eye()));
+                let camera = if let Some(camera) = per_view_camera(&gl_layer, &view, mirror) {
+                    camera
+                } else {
+                    continue;
+                };
+                let vp = camera.viewport.inset(*viewport_inset.borrow());
+                if vp != camera.viewport {
+                    clear_gutter(&gl, &camera.viewport, &vp, background);
+                }
+                cameras.push((view, camera, vp));
+            }
+            if let Some((_, camera, _)) = cameras.first() {
+                last_view.replace(Some((
+                    math::to_mat4(&camera.view),
+                    math::to_mat4(&camera.projection),
+                )));
+            }
+            let mut multiview_renderer = multiview_renderer.as_mut().filter(|_| {
+                *multiview.borrow()
+                    && !occlude_planes
+                    && !occlude_depth
+                    && cameras.len() == multiview::VIEW_COUNT
+            });
+            if let Some(multiview_renderer) = multiview_renderer.as_mut() {
+                let multiview_cameras: Vec<_> = cameras
+                    .iter()
+                    .map(|(_, camera, vp)| (&camera.view[..], &camera.projection[..], *vp))
+                    .collect();
+                multiview_renderer.draw_meshes(
+                    &gl,
+                    mesh_renderer,
+                    &multiview_cameras,
+                    clear_mask,
+                    plane,
+                    &mut gpu_memory.borrow_mut(),
+                );
+                gl.use_program(Some(&shader_profram));
+            }
+            for (i, (view, camera, vp)) in cameras.iter().enumerate() {
+                let target_vp = if let Some(multiview_renderer) = multiview_renderer.as_ref() {
+                    multiview_renderer.bind_view(&gl, i)
+                } else {
+                    gl.viewport(vp.x, vp.y, vp.width, vp.height);
+                    if let Some(plane_occluder) = plane_occluder.as_ref().filter(|_| occlude_planes)
+                    {
+                        plane_occluder.draw(&gl, &camera.view, &camera.projection);
+                        gl.use_program(Some(&shader_profram));
+                    }
+                    uniforms.uniform_matrix4fv(
+                        &gl,
+                        "projection",
+                        projection_location.as_ref(),
+                        &camera.projection,
+                    );
+                    uniforms.uniform_matrix4fv(&gl, "view", view_location.as_ref(), &camera.view);
+                    let depth_view = if occlude_depth {
+                        depth_textures.upload(&gl, &frame, view, i, &mut gpu_memory.borrow_mut())
+                    } else {
+                        None
+                    };
+                    uniforms.uniform1i(
+                        &gl,
+                        "depthEnabled",
+                        depth_enabled_location.as_ref(),
+                        depth_view.is_some() as i32,
+                    );
+                    if let Some(depth_view) = depth_view {
+                        uniforms.uniform_matrix4fv(
+                            &gl,
+                            "depthUvTransform",
+                            depth_uv_transform_location.as_ref(),
+                            &depth_view.window_to_uv(vp, mirror),
+                        );
+                        uniforms.uniform2f(
+                            &gl,
+                            "depthTexelWeights",
+                            depth_texel_weights_location.as_ref(),
+                            &depth_view.texel_weights(),
+                        );
+                    }
+                    mesh_renderer.draw(&gl, &mut uniforms);
+                    *vp
+                };
+                if let Some(line_renderer) = line_renderer.as_ref() {
+                    let screen_space = lines.borrow().screen_space;
+                    line_renderer.draw(
+                        &gl,
+                        &camera.view,
+                        &camera.projection,
+                        &target_vp,
+                        screen_space,
+                    );
+                    gl.use_program(Some(&shader_profram));
+                }
+                if let Some(ray_renderer) = ray_renderer.as_ref().filter(|_| draw_rays) {
+                    ray_renderer.draw(&gl, &camera.view, &camera.projection, &target_vp, false);
+                    gl.use_program(Some(&shader_profram));
+                }
+                if let Some(tap_renderer) = tap_renderer.as_ref().filter(|_| tap_drawn) {
+                    tap_renderer.draw(&gl, &camera.view, &camera.projection, &target_vp, false);
+                    gl.use_program(Some(&shader_profram));
+                }
+                if let Some(stereo_debug_renderer) = stereo_debug_renderer.as_ref() {
+                    if *stereo_debug.borrow() {
+                        stereo_debug_renderer.draw(
+                            &gl,
+                            &math::to_mat4(&camera.projection),
+                            view.eye(),
+                        );
+                        gl.use_program(Some(&shader_profram));
+                    }
+                }
+                if let Some(multiview_renderer) = multiview_renderer.as_ref() {
+                    multiview_renderer.blit_view(&gl, Some(&gl_layer.framebuffer()), vp);
+                }
+            }
+
+            request_next_frame(&sess, &f, &running, &frame_handle);
+        }) as Box<dyn FnMut(f64, XrFrame)>));
 
-    Ok(gl)
-}
+        self.running.replace(true);
+        self.frame_handle.replace(Some(request_animation_frame(
+            sess,
+            g.borrow().as_ref().unwrap(),
+        )));
+    }
 
-#[wasm_bindgen]
-pub struct XrApp {
-    session: Rc<RefCell<Option<XrSession>>>,
-    ref_space: Rc<RefCell<Option<XrReferenceSpace>>>,
-    gl: Rc<WebGl2RenderingContext>,
-}
+    // Features granted to the current session, which can be fewer than requested. Empty
+    // without a session or on runtimes that don't report them.
+    pub fn enabled_features(&self) -> Vec<String> {
+        let session = self.session.borrow();
+        let session = if let Some(session) = session.as_ref() {
+            session
+        } else {
+            return Vec::new();
+        };
+        analytics::enabled_features(session).unwrap_or_else(|| {
+            log!("warning: the runtime doesn't report enabled features");
+            Vec::new()
+        })
+    }
 
-#[wasm_bindgen]
-impl XrApp {
-    #[wasm_bindgen(constructor)]
-    pub fn new() -> XrApp {
-        console_error_panic_hook::set_once();
-        let session = Rc::new(RefCell::new(None));
-        let ref_space = Rc::new(RefCell::new(None));
+    // Nominal refresh rate of the display in Hz, when the runtime reports it.
+    pub fn frame_rate(&self) -> Option<f32> {
+        self.session
+            .borrow()
+            .as_ref()
+            .and_then(frame_rate::frame_rate)
+    }
 
-        let xr_mode = true;
-        let gl = Rc::new(create_webgl_context(xr_mode).unwrap());
+    // Frame rates that `request_frame_rate` accepts, or None without frame rate control.
+    pub fn supported_frame_rates(&self) -> Option<Vec<f32>> {
+        self.session
+            .borrow()
+            .as_ref()
+            .and_then(frame_rate::supported_frame_rates)
+    }
 
-        XrApp {
-            session,
-            ref_space,
-            gl,
+    // Asks the runtime to run at `hz`, trading battery for smoothness. Resolves once the new
+    // rate is in effect.
+    pub fn request_frame_rate(&self, hz: f32) -> Promise {
+        match self.session.borrow().as_ref() {
+            Some(session) => frame_rate::request_frame_rate(session, hz),
+            None => Promise::reject(&JsValue::from("no session")),
         }
     }
 
-    pub fn init(&self) -> Promise {
-        log!("Starting WebXR...");
-        let navigator: web_sys::Navigator = web_sys::window().unwrap().navigator();
-        let xr = navigator.xr();
-        let session_mode = XrSessionMode::ImmersiveVr;
-        let session_supported_promise = xr.is_session_supported(session_mode);
-
-        let session = self.session.clone();
-        let ref_space = self.ref_space.clone();
-        let gl = self.gl.clone();
-
-        let future = async move {
-            let supports_session =
-                wasm_bindgen_futures::JsFuture::from(session_supported_promise).await;
-            let supports_session = supports_session.unwrap();
-            if supports_session == false {
-                log!("XR session not supported");
-                return Ok(JsValue::from(false));
+    // Starts hit testing along the viewer's forward ray for placing objects on real surfaces.
+    // Needs an immersive-ar session with the "hit-test" feature, e.g. requested through
+    // `init`'s optional features.
+    pub fn request_hit_test_source(&self) -> Promise {
+        let session = match self.session.borrow().as_ref() {
+            Some(session) => session.clone(),
+            None => return Promise::reject(&JsValue::from("no session")),
+        };
+        if *self.session_mode.borrow() != SessionMode::ImmersiveAr {
+            return Promise::reject(&JsValue::from("hit testing needs an immersive-ar session"));
+        }
+        // Runtimes that don't report enabled features are left to reject the request.
+        let granted = match self.session_info.borrow().as_ref() {
+            Some(info) if !info.enabled_features.is_empty() => info
+                .enabled_features
+                .iter()
+                .any(|feature| feature == "hit-test"),
+            _ => true,
+        };
+        if !granted {
+            return Promise::reject(&JsValue::from("the hit-test feature was not granted"));
+        }
+        let hit_test_source = self.hit_test_source.clone();
+        future_to_promise(async move {
+            let source = hit_test::request_viewer_source(&session).await?;
+            if let Some(previous) = hit_test_source.borrow_mut().replace(source) {
+                previous.cancel();
             }
+            Ok(JsValue::from(true))
+        })
+    }
 
-            let mut xr_session_init = XrSessionInit::new();
-            xr_session_init.optional_features(&JsValue::from_serde(&["bounded-floor"]).unwrap());
-            let xr_session_promise =
-                xr.request_session_with_options(session_mode, &xr_session_init);
-            let xr_session = wasm_bindgen_futures::JsFuture::from(xr_session_promise).await;
-            let xr_session: XrSession = xr_session.unwrap().into();
-
-            let xr_gl_layer = XrWebGlLayer::new_with_web_gl2_rendering_context(&xr_session, &gl)?;
-            let mut render_state_init = XrRenderStateInit::new();
-            render_state_init.base_layer(Some(&xr_gl_layer));
-            xr_session.update_render_state_with_state(&render_state_init);
-
-            let ref_space_promise =
-                xr_session.request_reference_space(XrReferenceSpaceType::BoundedFloor);
-            let xr_ref_space = wasm_bindgen_futures::JsFuture::from(ref_space_promise).await;
-            let xr_ref_space: XrReferenceSpace = xr_ref_space.unwrap().into();
+    // Column-major pose of the closest surface hit in the last frame, or None without a hit.
+    pub fn hit_test_pose(&self) -> Option<Vec<f32>> {
+        self.hit_pose.borrow().map(|pose| pose.to_vec())
+    }
 
-            let mut session = session.borrow_mut();
-            session.replace(xr_session);
+    // Creates an anchor at a column-major pose in the current reference space, such as
+    // `hit_test_pose`, so content stays attached to the real world as tracking improves.
+    // Resolves with the anchor id on the next frame; needs the "anchors" feature.
+    pub fn create_anchor_at(&self, matrix: &[f32]) -> Promise {
+        if matrix.len() != 16 {
+            return Promise::reject(&JsValue::from("a pose needs 16 floats"));
+        }
+        if self.session.borrow().is_none() {
+            return Promise::reject(&JsValue::from("no session"));
+        }
+        self.anchors.borrow_mut().request(math::to_mat4(matrix))
+    }
 
-            let mut ref_space = ref_space.borrow_mut();
-            ref_space.replace(xr_ref_space);
+    // Latest column-major pose of an anchor, or None once the runtime stopped tracking it.
+    pub fn anchor_pose(&self, id: u32) -> Option<Vec<f32>> {
+        self.anchors.borrow().pose(id).map(|pose| pose.to_vec())
+    }
 
-            Ok(JsValue::from(true))
-        };
+    // False for unknown ids and anchors the runtime dropped, which are never tracked again.
+    pub fn is_anchor_tracked(&self, id: u32) -> bool {
+        self.anchors.borrow().is_tracked(id)
+    }
 
-        future_to_promise(future)
+    pub fn delete_anchor(&self, id: u32) {
+        self.anchors.borrow_mut().remove(id);
     }
 
-    pub fn start(&self) {
-        let session: &Option<XrSession> = &self.session.borrow();
-        let sess: &XrSession = if let Some(sess) = session {
-            sess
+    // Lets the user place the mesh `geometry_id` on a real surface: it follows the hit test
+    // pose, hidden while nothing is hit, until a select or pinch confirms it. An anchor is
+    // then created there, the mesh stays attached to it, and `on_placed` is called. Starts a
+    // hit test source if there is none; needs an immersive-ar session with the "hit-test"
+    // and "anchors" features. Calling it again places another mesh; it ends with the session.
+    pub fn enable_placement_mode(&self, geometry_id: usize) -> Promise {
+        if self.meshes.borrow_mut().get_mut(geometry_id).is_none() {
+            return Promise::reject(&JsValue::from(format!("no mesh with id {}", geometry_id)));
+        }
+        if self.session.borrow().is_none() {
+            return Promise::reject(&JsValue::from("no session"));
+        }
+        let hit_test = if self.hit_test_source.borrow().is_some() {
+            Promise::resolve(&JsValue::from(true))
         } else {
-            return ();
+            self.request_hit_test_source()
         };
+        self.placement.replace(Some(Placement::new(geometry_id)));
+        hit_test
+    }
 
-        let f = Rc::new(RefCell::new(None));
-        let g = f.clone();
+    // Stops aiming. A mesh already placed stays attached to its anchor.
+    pub fn disable_placement_mode(&self) {
+        self.placement.replace(None);
+    }
 
-        let gl = self.gl.clone();
-        let ref_space = self.ref_space.clone();
+    // Called as callback(anchorId, poseMatrix) once a placed mesh is attached to its anchor.
+    pub fn set_on_placed(&self, callback: Option<js_sys::Function>) {
+        self.on_placed.replace(callback);
+    }
 
-        let shader_profram = gl.create_program().unwrap();
+    // Ends the session, stops the render loop and deletes the renderers' GPU objects. The
+    // returned promise resolves once the runtime has shut the session down.
+    pub fn end(&self) -> Promise {
+        let session = self.tear_down();
+        // Not running now, unlike during the session's own end event.
+        self.session_end_closure.borrow_mut().take();
+        match session {
+            Some(session) => session.end(),
+            None => Promise::resolve(&JsValue::UNDEFINED),
+        }
+    }
 
-        let vs = gl
-            .create_shader(WebGl2RenderingContext::VERTEX_SHADER)
-            .unwrap();
-        gl.shader_source(
-            &vs,
-            "#version 300 es
-uniform mat4 model;
-uniform mat4 view;
-uniform mat4 projection;
-in vec3 vertexPosition;
-in vec3 vertexColor;
-out vec3 vColor;
-void main() {
-    vColor = vertexColor;
-    gl_Position = projection * view * model * vec4(vertexPosition, 1.0);
-}",
-        );
-        gl.compile_shader(&vs);
-        gl.attach_shader(&shader_profram, &vs);
-
-        let fs = gl
-            .create_shader(WebGl2RenderingContext::FRAGMENT_SHADER)
-            .unwrap();
-        gl.shader_source(
-            &fs,
-            "#version 300 es
-precision highp float;
-in vec3 vColor;
-out vec4 fragmentColor;
-void main() {
-    fragmentColor = vec4(vColor,1);
-}",
-        );
-        gl.compile_shader(&fs);
-        gl.attach_shader(&shader_profram, &fs);
+    // Ends the session like `end`, which deletes the renderers, then deletes the programs and
+    // any buffers left. Call it before dropping an XrApp (for example on hot reload) so GPU
+    // resources aren't leaked; the app can't be started again afterwards.
+    pub fn dispose(&self) -> Promise {
+        let ended = self.end();
+        self.program.replace(None);
+        self.program_cache.borrow_mut().delete_all(&self.gl);
+        self.gpu_memory.borrow_mut().delete_all(&self.gl);
+        self.uniforms.borrow_mut().invalidate();
+        ended
+    }
+}
 
-        gl.link_program(&shader_profram);
+impl XrApp {
+    // Resets everything tied to the current session, whether the app or the runtime ended it,
+    // and returns the session if there still was one. Every session handler is detached, so
+    // the end callback is called from here.
+    fn tear_down(&self) -> Option<XrSession> {
+        self.running.replace(false);
+        if let Some(repeat) = self.select_repeat.borrow_mut().as_mut() {
+            repeat.reset();
+        }
+        self.anchors.borrow_mut().clear();
+        if let Some(source) = self.hit_test_source.borrow_mut().take() {
+            source.cancel();
+        }
+        self.hit_pose.replace(None);
+        self.pinches.borrow_mut().reset();
+        self.tap_feedback.borrow_mut().reset();
+        self.placement.replace(None);
+        self.ref_space.replace(None);
+        self.recenter_spawn.replace(None);
+        self.input_sources.borrow_mut().clear();
+        self.input_poses.borrow_mut().clear();
+        self.hand_poses.borrow_mut().clear();
+        self.viewer_pose.replace(None);
+        self.delete_renderers();
+        let session = self.session.borrow_mut().take()?;
+        if let Some(handle) = self.frame_handle.borrow_mut().take() {
+            session.cancel_animation_frame(handle);
+        }
+        // The next init registers fresh handlers, so these would only pile up.
+        session.set_onselect(None);
+        session.set_onselectstart(None);
+        session.set_onselectend(None);
+        session.set_onend(None);
+        session.set_onvisibilitychange(None);
+        session.set_oninputsourceschange(None);
+        self.input_event_closures.borrow_mut().clear();
+        self.visibility_change_closure.borrow_mut().take();
+        self.input_sources_change_closure.borrow_mut().take();
+        let info = self.session_info.borrow_mut().take();
+        if let Some(info) = info {
+            analytics::call_session_callback(&self.on_session_end.borrow().clone(), &info);
+        }
+        Some(session)
+    }
 
-        if !gl
-            .get_program_parameter(&shader_profram, WebGl2RenderingContext::LINK_STATUS)
-            .as_bool()
-            .unwrap_or(false)
-        {
-            log!(
-                "program link errror:{}",
-                gl.get_program_info_log(&shader_profram).unwrap()
-            );
+    fn delete_renderers(&self) {
+        if let Some(renderers) = self.renderers.borrow_mut().take() {
+            renderers.delete(&self.gl, &mut self.gpu_memory.borrow_mut());
+        }
+    }
 
-            if !gl
-                .get_shader_parameter(&vs, WebGl2RenderingContext::COMPILE_STATUS)
-                .as_bool()
-                .unwrap_or(false)
-            {
-                log!("vs compile errror:{}", gl.get_shader_info_log(&vs).unwrap());
-            }
+    fn event_callback(&self, event: &str) -> Result<&RefCell<Option<js_sys::Function>>, JsValue> {
+        let callback = match event {
+            "start" => &self.on_session_start,
+            "end" => &self.on_session_end,
+            "frame" => &self.on_frame,
+            "select" => &self.on_select,
+            "selectstart" => &self.on_select_start,
+            "selectend" => &self.on_select_end,
+            "inputsourceschange" => &self.on_input_sources_change,
+            "inputadded" => &self.on_input_added,
+            "inputremoved" => &self.on_input_removed,
+            "visibilitychange" => &self.on_visibility_change,
+            "gesture" => &self.on_gesture,
+            "qualitychange" => &self.on_quality_change,
+            "referencespacechange" => &self.on_reference_space_change,
+            "trackinglost" => &self.on_tracking_lost,
+            "pinchstart" => &self.on_pinch_start,
+            "pinchend" => &self.on_pinch_end,
+            "placed" => &self.on_placed,
+            _ => return Err(JsValue::from(format!("unknown event: {}", event))),
+        };
+        Ok(callback)
+    }
 
-            if !gl
-                .get_shader_parameter(&fs, WebGl2RenderingContext::COMPILE_STATUS)
-                .as_bool()
-                .unwrap_or(false)
-            {
-                log!("fs compile errror:{}", gl.get_shader_info_log(&fs).unwrap());
-            }
+    fn listen_for_drag_look(&self) -> Result<(), JsValue> {
+        if !self.drag_look_closures.borrow().is_empty() {
+            return Ok(());
         }
+        let canvas: HtmlCanvasElement = self
+            .gl
+            .canvas()
+            .ok_or_else(|| JsValue::from("the WebGL context has no canvas"))?
+            .dyn_into()?;
 
-        gl.enable(WebGl2RenderingContext::DEPTH_TEST);
-        gl.enable(WebGl2RenderingContext::CULL_FACE);
-        gl.use_program(Some(&shader_profram));
+        let drag_look = self.drag_look.clone();
+        let press = Closure::wrap(Box::new(move |event: PointerEvent| {
+            drag_look
+                .borrow_mut()
+                .press(event.client_x(), event.client_y());
+        }) as Box<dyn FnMut(PointerEvent)>);
+        let drag_look = self.drag_look.clone();
+        let drag = Closure::wrap(Box::new(move |event: PointerEvent| {
+            drag_look
+                .borrow_mut()
+                .drag(event.client_x(), event.client_y());
+        }) as Box<dyn FnMut(PointerEvent)>);
+        let drag_look = self.drag_look.clone();
+        let release = Closure::wrap(Box::new(move |_event: PointerEvent| {
+            drag_look.borrow_mut().release();
+        }) as Box<dyn FnMut(PointerEvent)>);
 
-        let model_location = gl.get_uniform_location(&shader_profram, "model").unwrap();
-        let view_location = gl.get_uniform_location(&shader_profram, "view").unwrap();
-        let projection_location = gl
-            .get_uniform_location(&shader_profram, "projection")
-            .unwrap();
+        canvas.add_event_listener_with_callback("pointerdown", press.as_ref().unchecked_ref())?;
+        canvas.add_event_listener_with_callback("pointermove", drag.as_ref().unchecked_ref())?;
+        for event in ["pointerup", "pointercancel", "pointerleave"].iter() {
+            canvas.add_event_listener_with_callback(event, release.as_ref().unchecked_ref())?;
+        }
+        // Keeps touch drags from scrolling the page instead.
+        canvas.style().set_property("touch-action", "none")?;
+        self.drag_look_closures
+            .borrow_mut()
+            .extend(vec![press, drag, release]);
+        Ok(())
+    }
 
-        let vertices: [f32; 18] = [
-            -0.7, -0.7, 0.0, 1., 0., 0., 0.7, -0.7, 0.0, 0., 1., 0., 0.0, 0.7, 0.0, 0., 0., 1.,
-        ];
-        let vb = gl.create_buffer().unwrap();
-        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vb));
-        unsafe {
-            let vertexies = js_sys::Float32Array::view(&vertices);
-            gl.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &vertexies,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
+    fn request_session(
+        &self,
+        session_mode: SessionMode,
+        ref_space_type: XrReferenceSpaceType,
+        required_features: &[String],
+        optional_features: &[String],
+    ) -> Promise {
+        log!("Starting WebXR...");
+        let navigator: web_sys::Navigator = match web_sys::window() {
+            Some(window) => window.navigator(),
+            None => return Promise::reject(&JsValue::from("no window to request a session from")),
+        };
+        let xr: JsValue = navigator.xr().into();
+        // Browsers without WebXR, or pages not served over a secure context, have no
+        // navigator.xr and calling into it would throw.
+        if xr.is_undefined() {
+            return Promise::reject(&JsValue::from("WebXR is not available"));
         }
+        let xr: XrModes = xr.unchecked_into();
+        let session_supported_promise = xr.is_session_supported(session_mode.as_str());
+        let required_feature_names = required_features.join(", ");
+        let depth_sensing =
+            depth::is_requested(required_features) || depth::is_requested(optional_features);
+        let required_features: js_sys::Array =
+            required_features.iter().map(JsValue::from).collect();
+        let optional_features: js_sys::Array =
+            optional_features.iter().map(JsValue::from).collect();
 
-        let vertex_attrib_location = gl.get_attrib_location(&shader_profram, "vertexPosition");
+        let session = self.session.clone();
+        let ref_space = self.ref_space.clone();
+        let gl = self.gl.clone();
+        let quality = *self.quality.borrow();
+        let layer_options = *self.layer_options.borrow();
+        let framebuffer_scale = *self.framebuffer_scale.borrow();
+        let on_select = self.on_select.clone();
+        let on_select_start = self.on_select_start.clone();
+        let on_select_end = self.on_select_end.clone();
+        let input_event_closures = self.input_event_closures.clone();
+        let select_repeat = self.select_repeat.clone();
+        let pointer_tilt = self.pointer_tilt.clone();
+        let swap_handedness = self.swap_handedness.clone();
+        let input_sources = self.input_sources.clone();
+        let on_input_added = self.on_input_added.clone();
+        let on_input_removed = self.on_input_removed.clone();
+        let input_sources_change_closure = self.input_sources_change_closure.clone();
+        let session_timeout_ms = *self.session_timeout_ms.borrow();
+        let session_info = self.session_info.clone();
+        let app = self.clone();
+        let session_end_closure = self.session_end_closure.clone();
+        let visible = self.visible.clone();
+        let visibility_change_closure = self.visibility_change_closure.clone();
+        let on_visibility_change = self.on_visibility_change.clone();
+        let on_input_sources_change = self.on_input_sources_change.clone();
+        let current_session_mode = self.session_mode.clone();
+        let current_ref_space_type = self.ref_space_type.clone();
+        let tap_feedback = self.tap_feedback.clone();
+        let frame_timing = self.frame_timing.clone();
+        let placement = self.placement.clone();
+        let hit_pose = self.hit_pose.clone();
+        let anchors = self.anchors.clone();
+        let on_placed = self.on_placed.clone();
 
-        gl.enable_vertex_attrib_array(vertex_attrib_location as u32);
-        gl.enable_vertex_attrib_array(1);
+        let future = async move {
+            let supports_session =
+                wasm_bindgen_futures::JsFuture::from(session_supported_promise).await;
+            let supports_session = supports_session?;
+            if supports_session == false {
+                log!("XR session not supported");
+                return Ok(JsValue::from(false));
+            }
 
-        gl.vertex_attrib_pointer_with_i32(
-            0,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            (3 + 3) * 4,
-            0,
-        );
-        gl.vertex_attrib_pointer_with_i32(
-            1,
-            3,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            (3 + 3) * 4,
-            3 * 4,
-        );
+            let mut xr_session_init = XrSessionInit::new();
+            xr_session_init
+                .required_features(&required_features)
+                .optional_features(&optional_features);
+            if depth_sensing {
+                depth::request_depth_sensing(&xr_session_init)?;
+            }
+            let xr_session_promise = xr.request_session(session_mode.as_str(), &xr_session_init);
+            let xr_session = with_timeout(xr_session_promise, session_timeout_ms).await;
+            let xr_session: XrSession = match xr_session {
+                Ok(xr_session) => xr_session.into(),
+                // Most likely an unsupported required feature, which the runtime doesn't name.
+                Err(err) if !required_feature_names.is_empty() => {
+                    return Err(JsValue::from(format!(
+                        "session request failed, required features may be unsupported ({}): {:?}",
+                        required_feature_names, err
+                    )))
+                }
+                Err(err) => return Err(err),
+            };
 
-        *g.borrow_mut() = Some(Closure::wrap(Box::new(move |_time: f64, frame: XrFrame| {
-            let sess: XrSession = frame.session();
+            let layer = apply_xr_layer(
+                &xr_session,
+                &gl,
+                &quality,
+                &layer_options,
+                framebuffer_scale,
+            )?;
+            // Inline canvases follow their displayed size instead.
+            if session_mode != SessionMode::Inline {
+                size_canvas_to_layer(&gl, &layer);
+            }
 
-            let gl_layer = sess.render_state().base_layer().unwrap();
+            let mut ref_space_type = ref_space_type;
+            let xr_ref_space: XrReferenceSpace = loop {
+                let ref_space_promise = xr_session.request_reference_space(ref_space_type);
+                match wasm_bindgen_futures::JsFuture::from(ref_space_promise).await {
+                    Ok(xr_ref_space) => {
+                        log!("reference space: {:?}", ref_space_type);
+                        break xr_ref_space.into();
+                    }
+                    Err(err) => match tracking::fallback(ref_space_type) {
+                        Some(fallback) => {
+                            log!("{:?} unavailable, trying {:?}", ref_space_type, fallback);
+                            ref_space_type = fallback;
+                        }
+                        None => return Err(err),
+                    },
+                }
+            };
 
-            gl.bind_framebuffer(
-                WebGl2RenderingContext::FRAMEBUFFER,
-                Some(&gl_layer.framebuffer()),
-            );
-            gl.clear_color(0., 0., 0., 1.);
-            gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+            session_info.replace(Some(SessionInfo::new(
+                &xr_session,
+                session_mode,
+                ref_space_type,
+            )));
+            // Ended by the runtime or the user, e.g. with the headset's exit button. The closure
+            // is kept until the next session replaces it, as it can't be dropped while running.
+            let closure = Closure::wrap(Box::new(move |event: XrSessionEvent| {
+                if app.session.borrow().as_ref() == Some(&event.session()) {
+                    app.tear_down();
+                }
+            }) as Box<dyn FnMut(XrSessionEvent)>);
+            xr_session.set_onend(Some(closure.as_ref().unchecked_ref()));
+            session_end_closure.borrow_mut().replace(closure);
 
-            let ref_pose = ref_space.borrow();
-            let pose = frame.get_viewer_pose(&ref_pose.as_ref().unwrap()).unwrap();
-            let views = pose.views();
-            gl.uniform_matrix4fv_with_f32_array(
-                Some(&model_location),
-                false,
-                &[
-                    2., 0., 0., 0., 0., 2., 0., 0., 0., 0., 2., 0., 0., 0., 0., 1.,
-                ],
-            );
-            {
-                let view: XrView = views.get(0).into();
-                let vp = gl_layer.get_viewport(&view).unwrap();
-                gl.viewport(vp.x(), vp.y(), vp.width(), vp.height());
-
-                gl.uniform_matrix4fv_with_f32_array(
-                    Some(&projection_location),
-                    false,
-                    &view.projection_matrix(),
-                );
-                gl.uniform_matrix4fv_with_f32_array(
-                    Some(&view_location),
-                    false,
-                    &view.transform().inverse().matrix(),
-                );
-                gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, (3) as i32);
-            }
-            {
-                let view: XrView = views.get(1).into();
-                let vp = gl_layer.get_viewport(&view).unwrap();
-                gl.viewport(vp.x(), vp.y(), vp.width(), vp.height());
-
-                gl.uniform_matrix4fv_with_f32_array(
-                    Some(&projection_location),
-                    false,
-                    &view.projection_matrix(),
+            let is_visible =
+                |session: &XrSession| session.visibility_state() != XrVisibilityState::Hidden;
+            visible.replace(is_visible(&xr_session));
+            let closure = Closure::wrap(Box::new(move |event: XrSessionEvent| {
+                let session = event.session();
+                visible.replace(is_visible(&session));
+                let callback = on_visibility_change.borrow().clone();
+                if let Some(callback) = callback {
+                    let state = JsValue::from(session.visibility_state());
+                    if let Err(err) = callback.call1(&JsValue::NULL, &state) {
+                        log!("visibility change callback error:{:?}", err);
+                    }
+                }
+            }) as Box<dyn FnMut(XrSessionEvent)>);
+            xr_session.set_onvisibilitychange(Some(closure.as_ref().unchecked_ref()));
+            visibility_change_closure.borrow_mut().replace(closure);
+
+            let select_ref_space = ref_space.clone();
+            let select_pointer_tilt = pointer_tilt.clone();
+            let select_swap_handedness = swap_handedness.clone();
+            let closure = Closure::wrap(Box::new(move |event: XrInputSourceEvent| {
+                let ref_space = select_ref_space.borrow().clone();
+                if let Some(ref_space) = ref_space {
+                    call_select_callback(
+                        &on_select,
+                        &event.frame(),
+                        &event.input_source(),
+                        &ref_space,
+                        *select_pointer_tilt.borrow(),
+                        *select_swap_handedness.borrow(),
+                    );
+                }
+                confirm_placement(&placement, &hit_pose, &anchors, &on_placed);
+            }) as Box<dyn FnMut(XrInputSourceEvent)>);
+            xr_session.set_onselect(Some(closure.as_ref().unchecked_ref()));
+            input_event_closures.borrow_mut().push(closure);
+
+            let select_start_repeat = select_repeat.clone();
+            let select_start_swap_handedness = swap_handedness.clone();
+            let select_start_ref_space = ref_space.clone();
+            let select_start_tap_feedback = tap_feedback.clone();
+            let closure = Closure::wrap(Box::new(move |event: XrInputSourceEvent| {
+                let input_source = event.input_source();
+                if let Some(repeat) = select_start_repeat.borrow_mut().as_mut() {
+                    repeat.press(&input_source);
+                }
+                if input_source.target_ray_mode() == XrTargetRayMode::Screen {
+                    let target_ray =
+                        select_start_ref_space
+                            .borrow()
+                            .as_ref()
+                            .and_then(|ref_space| {
+                                input_pose::target_ray(&event.frame(), &input_source, ref_space, 0.)
+                            });
+                    if let Some(target_ray) = target_ray {
+                        select_start_tap_feedback.borrow_mut().press(target_ray);
+                    }
+                }
+                call_handedness_callback(
+                    &on_select_start,
+                    &input_source,
+                    *select_start_swap_handedness.borrow(),
                 );
-                gl.uniform_matrix4fv_with_f32_array(
-                    Some(&view_location),
-                    false,
-                    &view.transform().inverse().matrix(),
+            }) as Box<dyn FnMut(XrInputSourceEvent)>);
+            xr_session.set_onselectstart(Some(closure.as_ref().unchecked_ref()));
+            input_event_closures.borrow_mut().push(closure);
+
+            let select_end_repeat = select_repeat.clone();
+            let select_end_swap_handedness = swap_handedness.clone();
+            let closure = Closure::wrap(Box::new(move |event: XrInputSourceEvent| {
+                let input_source = event.input_source();
+                if let Some(repeat) = select_end_repeat.borrow_mut().as_mut() {
+                    repeat.release(&input_source);
+                }
+                if input_source.target_ray_mode() == XrTargetRayMode::Screen {
+                    tap_feedback
+                        .borrow_mut()
+                        .release(frame_timing.borrow().time);
+                }
+                call_handedness_callback(
+                    &on_select_end,
+                    &input_source,
+                    *select_end_swap_handedness.borrow(),
                 );
-                gl.draw_arrays(WebGlRenderingContext::TRIANGLES, 0, (3) as i32);
-            }
+            }) as Box<dyn FnMut(XrInputSourceEvent)>);
+            xr_session.set_onselectend(Some(closure.as_ref().unchecked_ref()));
+            input_event_closures.borrow_mut().push(closure);
 
-            request_animation_frame(&sess, f.borrow().as_ref().unwrap());
-        }) as Box<dyn FnMut(f64, XrFrame)>));
+            // Sources left over from a previous session are dropped. Sources already connected
+            // are also reported by the first inputsourceschange, which skips duplicates.
+            let connected = xr_session.input_sources();
+            input_sources.replace(
+                (0..connected.length())
+                    .filter_map(|i| connected.get(i))
+                    .collect(),
+            );
+            let closure = Closure::wrap(Box::new(move |event: XrInputSourcesChangeEvent| {
+                let removed: Vec<XrInputSource> =
+                    event.removed().iter().map(|source| source.into()).collect();
+                let added: Vec<XrInputSource> =
+                    event.added().iter().map(|source| source.into()).collect();
+                if let Some(repeat) = select_repeat.borrow_mut().as_mut() {
+                    for source in &removed {
+                        repeat.release(source);
+                    }
+                }
+                {
+                    let mut sources = input_sources.borrow_mut();
+                    sources.retain(|source| !removed.contains(source));
+                    for source in &added {
+                        if !sources.contains(source) {
+                            sources.push(source.clone());
+                        }
+                    }
+                }
+                // Callbacks run after the list is updated so they can query it.
+                call_input_source_callback(&on_input_removed, &removed);
+                call_input_source_callback(&on_input_added, &added);
+                let callback = on_input_sources_change.borrow().clone();
+                if let Some(callback) = callback {
+                    if let Err(err) =
+                        callback.call2(&JsValue::NULL, &event.added(), &event.removed())
+                    {
+                        log!("input sources change callback error:{:?}", err);
+                    }
+                }
+            })
+                as Box<dyn FnMut(XrInputSourcesChangeEvent)>);
+            xr_session.set_oninputsourceschange(Some(closure.as_ref().unchecked_ref()));
+            input_sources_change_closure.borrow_mut().replace(closure);
+
+            current_session_mode.replace(session_mode);
+            current_ref_space_type.replace(ref_space_type);
+
+            let mut session = session.borrow_mut();
+            session.replace(xr_session);
 
-        request_animation_frame(sess, g.borrow().as_ref().unwrap());
+            let mut ref_space = ref_space.borrow_mut();
+            ref_space.replace(xr_ref_space);
+
+            Ok(JsValue::from(true))
+        };
+
+        future_to_promise(future)
     }
 }
 
@@ -313,10 +2799,14 @@ macro_rules! impl_webgl_trait {
     ($trait_name:ident implementors {$($implementor:ident;)*} methods {$(fn $method:ident($($arg_name:ident: $arg_type:ty),*) -> $result_type:ty;)*}) => {
         impl_webgl_trait!{trait $trait_name {$(fn $method($($arg_name: $arg_type),*) -> $result_type;)*}}
         impl_webgl_trait!{impl $trait_name implementors {$($implementor;)*} methods {$(fn $method($($arg_name: $arg_type),*) -> $result_type;)*}}
+        #[cfg(test)]
+        impl_webgl_trait!{mock $trait_name methods {$(fn $method($($arg_name: $arg_type),*) -> $result_type;)*}}
     };
     (trait $trait_name:ident {$(fn $method:ident($($arg_name:ident: $arg_type:ty),*) -> $result_type:ty;)*}) => {
         pub trait $trait_name {
             $(
+                // Signatures mirror web-sys, however many arguments they take.
+                #[allow(clippy::too_many_arguments)]
                 fn $method(&self, $($arg_name: $arg_type),*) -> $result_type;
             )*
         }
@@ -332,6 +2822,26 @@ macro_rules! impl_webgl_trait {
         impl_webgl_trait!{impl $trait_name implementors {$($impl_tail;)*} methods {$(fn $method($($arg_name: $arg_type),*) -> $result_type;)*}}
     };
     (impl $trait_name:ident implementors {} methods {$(fn $method:ident($($arg_name:ident: $arg_type:ty),*) -> $result_type:ty;)*}) => {};
+    (mock $trait_name:ident methods {$(fn $method:ident($($arg_name:ident: $arg_type:ty),*) -> $result_type:ty;)*}) => {
+        // Context for tests that records each call as "method(arguments)" and returns nothing,
+        // None or zero.
+        #[derive(Default)]
+        pub struct RecordingGl {
+            pub calls: RefCell<Vec<String>>,
+        }
+
+        impl $trait_name for RecordingGl {
+            $(
+                fn $method(&self, $($arg_name: $arg_type),*) -> $result_type {
+                    self.calls.borrow_mut().push(format!(
+                        concat!(stringify!($method), "{:?}"),
+                        ($($arg_name,)*)
+                    ));
+                    tests::MockResult::mock_result()
+                }
+            )*
+        }
+    };
 }
 
 impl_webgl_trait! {
@@ -341,9 +2851,11 @@ impl_webgl_trait! {
         WebGl2RenderingContext;
     }
     methods {
+        fn active_texture(texture: u32) -> ();
         fn attach_shader(program: &WebGlProgram, shader: &WebGlShader) -> ();
         fn bind_buffer(target: u32, buffer: Option<&WebGlBuffer>) -> ();
         fn bind_framebuffer(target: u32, framebuffer: Option<&WebGlFramebuffer>) -> ();
+        fn bind_texture(target: u32, texture: Option<&WebGlTexture>) -> ();
         fn blend_func(sfactor: u32, dfactor: u32) -> ();
         fn buffer_data_with_array_buffer_view(target: u32, src_data: &js_sys::Object, usage: u32) -> ();
         fn buffer_data_with_i32(target: u32, size: i32, usage: u32) -> ();
@@ -353,26 +2865,240 @@ impl_webgl_trait! {
         fn create_buffer() -> Option<WebGlBuffer>;
         fn create_program() -> Option<WebGlProgram>;
         fn create_shader(type_: u32) -> Option<WebGlShader>;
+        fn create_texture() -> Option<WebGlTexture>;
+        fn delete_texture(texture: Option<&WebGlTexture>) -> ();
         fn depth_func(func: u32) -> ();
         fn disable(cap: u32) -> ();
         fn draw_arrays(mode: u32, first: i32, count: i32) -> ();
+        fn draw_elements_with_i32(mode: u32, count: i32, type_: u32, offset: i32) -> ();
         fn enable(cap: u32) -> ();
         fn enable_vertex_attrib_array(index: u32) -> ();
+        fn framebuffer_texture_2d(target: u32, attachment: u32, textarget: u32, texture: Option<&WebGlTexture>, level: i32) -> ();
+        fn generate_mipmap(target: u32) -> ();
         fn get_active_attrib(program: &WebGlProgram, index: u32) -> Option<WebGlActiveInfo>;
         fn get_active_uniform(program: &WebGlProgram, index: u32) -> Option<WebGlActiveInfo>;
         fn get_attrib_location(program: &WebGlProgram, name: &str) -> i32;
+        fn get_parameter(pname: u32) -> Result<wasm_bindgen::JsValue, wasm_bindgen::JsValue>;
         fn get_program_info_log(program: &WebGlProgram) -> Option<String>;
         fn get_program_parameter(program: &WebGlProgram, pname: u32) -> wasm_bindgen::JsValue;
         fn get_shader_info_log(shader: &WebGlShader) -> Option<String>;
         fn get_shader_parameter(shader: &WebGlShader, pname: u32) -> wasm_bindgen::JsValue;
+        fn get_supported_extensions() -> Option<js_sys::Array>;
         fn get_uniform_location(program: &WebGlProgram, name: &str) -> Option<WebGlUniformLocation>;
         fn link_program(program: &WebGlProgram) -> ();
+        fn renderbuffer_storage(target: u32, internalformat: u32, width: i32, height: i32) -> ();
         fn shader_source(shader: &WebGlShader, source: &str) -> ();
+        fn tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(target: u32, level: i32, internalformat: i32, width: i32, height: i32, border: i32, format: u32, type_: u32, pixels: Option<&[u8]>) -> Result<(), wasm_bindgen::JsValue>;
+        fn tex_parameteri(target: u32, pname: u32, param: i32) -> ();
         fn uniform1f(location: Option<&WebGlUniformLocation>, x: f32) -> ();
+        fn uniform1i(location: Option<&WebGlUniformLocation>, x: i32) -> ();
         fn uniform2f(location: Option<&WebGlUniformLocation>, x: f32, y: f32) -> ();
+        fn uniform3f(location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32) -> ();
+        fn uniform4f(location: Option<&WebGlUniformLocation>, x: f32, y: f32, z: f32, w: f32) -> ();
         fn uniform_matrix4fv_with_f32_array(location: Option<&WebGlUniformLocation>, transpose: bool, data: &[f32]) -> ();
         fn use_program(program: Option<&WebGlProgram>) -> ();
         fn viewport(x: i32, y: i32, width: i32, height: i32) -> ();
         fn vertex_attrib_pointer_with_i32(index: u32, size: i32, type_: u32, normalized: bool, stride: i32, offset: i32) -> ();
     }
 }
+
+// Selects the color attachments fragment outputs are written to. Core in WebGL2; WebGL1 needs
+// the WEBGL_draw_buffers extension and returns an error without it.
+pub trait DrawBuffers {
+    fn draw_buffers(&self, buffers: &js_sys::Array) -> Result<(), JsValue>;
+}
+
+impl DrawBuffers for WebGl2RenderingContext {
+    fn draw_buffers(&self, buffers: &js_sys::Array) -> Result<(), JsValue> {
+        Self::draw_buffers(self, buffers);
+        Ok(())
+    }
+}
+
+impl DrawBuffers for WebGlRenderingContext {
+    fn draw_buffers(&self, buffers: &js_sys::Array) -> Result<(), JsValue> {
+        let extension = self
+            .get_extension("WEBGL_draw_buffers")?
+            .ok_or_else(|| JsValue::from("WEBGL_draw_buffers is not supported"))?;
+        extension
+            .unchecked_into::<WebglDrawBuffers>()
+            .draw_buffers_webgl(buffers);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub trait MockResult {
+        fn mock_result() -> Self;
+    }
+
+    impl MockResult for () {
+        fn mock_result() -> Self {}
+    }
+
+    impl MockResult for i32 {
+        fn mock_result() -> Self {
+            0
+        }
+    }
+
+    impl<T> MockResult for Option<T> {
+        fn mock_result() -> Self {
+            None
+        }
+    }
+
+    impl MockResult for JsValue {
+        fn mock_result() -> Self {
+            JsValue::UNDEFINED
+        }
+    }
+
+    impl<T: MockResult> MockResult for Result<T, JsValue> {
+        fn mock_result() -> Self {
+            Ok(T::mock_result())
+        }
+    }
+
+    // Attaches a color texture and a depth renderbuffer the way a render-to-texture pass would,
+    // through the trait only.
+    fn attach_render_targets<T: GlContext>(gl: &T) {
+        gl.framebuffer_texture_2d(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            WebGl2RenderingContext::COLOR_ATTACHMENT0,
+            WebGl2RenderingContext::TEXTURE_2D,
+            None,
+            0,
+        );
+        gl.renderbuffer_storage(
+            WebGl2RenderingContext::RENDERBUFFER,
+            WebGl2RenderingContext::DEPTH_COMPONENT16,
+            256,
+            128,
+        );
+    }
+
+    #[test]
+    fn render_target_methods_forward_their_arguments() {
+        let gl = RecordingGl::default();
+        attach_render_targets(&gl);
+        assert_eq!(
+            *gl.calls.borrow(),
+            vec![
+                format!(
+                    "framebuffer_texture_2d({}, {}, {}, None, 0)",
+                    WebGl2RenderingContext::FRAMEBUFFER,
+                    WebGl2RenderingContext::COLOR_ATTACHMENT0,
+                    WebGl2RenderingContext::TEXTURE_2D
+                ),
+                format!(
+                    "renderbuffer_storage({}, {}, 256, 128)",
+                    WebGl2RenderingContext::RENDERBUFFER,
+                    WebGl2RenderingContext::DEPTH_COMPONENT16
+                ),
+            ]
+        );
+    }
+
+    // Sets a flag, a color and a plane the way the main and debug programs do, through the
+    // trait only.
+    fn set_uniforms<T: GlContext>(gl: &T) {
+        gl.uniform1i(None, 1);
+        gl.uniform3f(None, 1., 0.5, 0.);
+        gl.uniform4f(None, 0., 1., 0., -1.5);
+    }
+
+    #[test]
+    fn uniform_methods_forward_their_arguments() {
+        let gl = RecordingGl::default();
+        set_uniforms(&gl);
+        assert_eq!(
+            *gl.calls.borrow(),
+            vec![
+                "uniform1i(None, 1)",
+                "uniform3f(None, 1.0, 0.5, 0.0)",
+                "uniform4f(None, 0.0, 1.0, 0.0, -1.5)",
+            ]
+        );
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn draw_to_back_buffer<T: DrawBuffers>(gl: &T) -> Result<(), JsValue> {
+        gl.draw_buffers(&js_sys::Array::of1(&JsValue::from(
+            WebGl2RenderingContext::BACK,
+        )))
+    }
+
+    #[wasm_bindgen_test]
+    fn draw_buffers_selects_the_back_buffer() {
+        let gl = create_webgl_context(false, None).unwrap();
+        draw_to_back_buffer(&gl).unwrap();
+        assert_eq!(gl.get_error(), WebGl2RenderingContext::NO_ERROR);
+    }
+
+    // Replaces navigator.xr with a runtime that supports every mode but refuses sessions.
+    fn mock_refusing_xr() {
+        let xr = js_sys::Object::new();
+        let methods = [
+            ("isSessionSupported", "return Promise.resolve(true);"),
+            (
+                "requestSession",
+                "return Promise.reject(new Error('NotAllowedError'));",
+            ),
+        ];
+        for (name, body) in methods.iter() {
+            let method = js_sys::Function::new_no_args(body);
+            js_sys::Reflect::set(&xr, &JsValue::from(*name), &method).unwrap();
+        }
+        let descriptor = js_sys::Object::new();
+        js_sys::Reflect::set(&descriptor, &JsValue::from("value"), &xr).unwrap();
+        js_sys::Reflect::set(&descriptor, &JsValue::from("configurable"), &JsValue::TRUE).unwrap();
+        let navigator = web_sys::window().unwrap().navigator();
+        js_sys::Object::define_property(&navigator, &JsValue::from("xr"), &descriptor);
+    }
+
+    #[wasm_bindgen_test]
+    async fn init_rejects_when_the_session_is_refused() {
+        mock_refusing_xr();
+        let app = XrApp::new(None).unwrap();
+        let result = wasm_bindgen_futures::JsFuture::from(app.init(
+            Some(String::from("immersive-vr")),
+            None,
+            None,
+            None,
+            None,
+            None,
+        ))
+        .await;
+        assert!(result.is_err());
+        assert!(!*app.running.borrow());
+        assert!(app.session.borrow().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn canvas_apps_apply_the_config() {
+        let canvas: HtmlCanvasElement = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .unchecked_into();
+        let mut config = XrAppConfig::new();
+        config.set_clear_color(0.1, 0.2, 0.3, 1.);
+        config.set_framebuffer_scale(0.5);
+        let app = XrApp::with_canvas_and_config(&canvas, config).unwrap();
+        assert_eq!(*app.custom_clear_color.borrow(), Some([0.1, 0.2, 0.3, 1.]));
+        assert_eq!(app.config.framebuffer_scale, Some(0.5));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::math::Vec3;
+use crate::memory::{self, GpuMemory};
+use crate::shader::ProgramCache;
+use crate::viewport::Viewport;
+use web_sys::*;
+
+// Each segment is expanded into a camera-facing quad in the vertex shader.
+// Width is in meters, or in pixels when `screen_space` is enabled.
+const LINE_VS: &str = "#version 300 es
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec2 viewportSize;
+uniform bool screenSpace;
+in vec3 lineStart;
+in vec3 lineEnd;
+in float lineT;
+in float lineSide;
+in float lineWidth;
+in vec3 lineColor;
+out vec3 vColor;
+void main() {
+    vColor = lineColor;
+    vec4 viewStart = view * vec4(lineStart, 1.0);
+    vec4 viewEnd = view * vec4(lineEnd, 1.0);
+    vec4 viewPos = mix(viewStart, viewEnd, lineT);
+    if (screenSpace) {
+        vec4 clipStart = projection * viewStart;
+        vec4 clipEnd = projection * viewEnd;
+        vec2 dir = normalize((clipEnd.xy / clipEnd.w - clipStart.xy / clipStart.w) * viewportSize);
+        vec2 normal = vec2(-dir.y, dir.x);
+        vec4 clip = projection * viewPos;
+        clip.xy += normal * lineSide * lineWidth / viewportSize * clip.w;
+        gl_Position = clip;
+    } else {
+        vec3 dir = normalize(viewEnd.xyz - viewStart.xyz);
+        vec3 normal = normalize(cross(dir, normalize(-viewPos.xyz)));
+        viewPos.xyz += normal * lineSide * lineWidth * 0.5;
+        gl_Position = projection * viewPos;
+    }
+}";
+
+const LINE_FS: &str = "#version 300 es
+precision highp float;
+in vec3 vColor;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(vColor, 1);
+}";
+
+// start(3) end(3) t(1) side(1) width(1) color(3)
+const FLOATS_PER_VERTEX: usize = 12;
+const CORNERS: [(f32, f32); 6] = [
+    (0., 1.),
+    (0., -1.),
+    (1., 1.),
+    (1., 1.),
+    (0., -1.),
+    (1., -1.),
+];
+
+pub struct Line {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub width: f32,
+    pub color: Vec3,
+}
+
+#[derive(Default)]
+pub struct Lines {
+    lines: Vec<Line>,
+    dirty: bool,
+    pub screen_space: bool,
+}
+
+impl Lines {
+    pub fn add(&mut self, line: Line) -> usize {
+        self.lines.push(line);
+        self.dirty = true;
+        self.lines.len() - 1
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.dirty = true;
+    }
+
+    fn vertices(&self) -> Vec<f32> {
+        let mut vertices = Vec::with_capacity(self.lines.len() * CORNERS.len() * FLOATS_PER_VERTEX);
+        for line in &self.lines {
+            for (t, side) in CORNERS.iter() {
+                vertices.extend_from_slice(&line.start);
+                vertices.extend_from_slice(&line.end);
+                vertices.push(*t);
+                vertices.push(*side);
+                vertices.push(line.width);
+                vertices.extend_from_slice(&line.color);
+            }
+        }
+        vertices
+    }
+}
+
+pub struct LineRenderer {
+    program: WebGlProgram,
+    vao: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    vertex_count: i32,
+    view_location: Option<WebGlUniformLocation>,
+    projection_location: Option<WebGlUniformLocation>,
+    viewport_size_location: Option<WebGlUniformLocation>,
+    screen_space_location: Option<WebGlUniformLocation>,
+}
+
+impl LineRenderer {
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program_cache: &mut ProgramCache,
+    ) -> Result<LineRenderer, String> {
+        let program = program_cache.get_or_link(gl, LINE_VS, LINE_FS)?;
+
+        let vao = gl
+            .create_vertex_array()
+            .ok_or_else(|| String::from("unable to create vertex array"))?;
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("unable to create buffer"))?;
+        gl.bind_vertex_array(Some(&vao));
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+        let stride = (FLOATS_PER_VERTEX * 4) as i32;
+        let attributes = [
+            ("lineStart", 3, 0),
+            ("lineEnd", 3, 3),
+            ("lineT", 1, 6),
+            ("lineSide", 1, 7),
+            ("lineWidth", 1, 8),
+            ("lineColor", 3, 9),
+        ];
+        for (name, size, offset) in attributes.iter() {
+            let location = gl.get_attrib_location(&program, name);
+            if location < 0 {
+                continue;
+            }
+            gl.enable_vertex_attrib_array(location as u32);
+            gl.vertex_attrib_pointer_with_i32(
+                location as u32,
+                *size,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                stride,
+                offset * 4,
+            );
+        }
+        gl.bind_vertex_array(None);
+
+        Ok(LineRenderer {
+            view_location: gl.get_uniform_location(&program, "view"),
+            projection_location: gl.get_uniform_location(&program, "projection"),
+            viewport_size_location: gl.get_uniform_location(&program, "viewportSize"),
+            screen_space_location: gl.get_uniform_location(&program, "screenSpace"),
+            program,
+            vao,
+            buffer,
+            vertex_count: 0,
+        })
+    }
+
+    pub fn sync(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        lines: &mut Lines,
+        gpu_memory: &mut GpuMemory,
+    ) {
+        if !lines.dirty {
+            return;
+        }
+        let vertices = lines.vertices();
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buffer));
+        unsafe {
+            let vertices = js_sys::Float32Array::view(&vertices);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vertices,
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+            );
+        }
+        gpu_memory.buffer_data(&self.buffer, memory::buffer_bytes(vertices.len(), 4));
+        self.vertex_count = (vertices.len() / FLOATS_PER_VERTEX) as i32;
+        lines.dirty = false;
+    }
+
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        gl.delete_vertex_array(Some(&self.vao));
+        gpu_memory.delete(gl, &self.buffer);
+    }
+
+    pub fn draw(
+        &self,
+        gl: &WebGl2RenderingContext,
+        view: &[f32],
+        projection: &[f32],
+        viewport: &Viewport,
+        screen_space: bool,
+    ) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+        gl.uniform_matrix4fv_with_f32_array(self.view_location.as_ref(), false, view);
+        gl.uniform_matrix4fv_with_f32_array(self.projection_location.as_ref(), false, projection);
+        gl.uniform2f(
+            self.viewport_size_location.as_ref(),
+            viewport.width as f32,
+            viewport.height as f32,
+        );
+        gl.uniform1i(self.screen_space_location.as_ref(), screen_space as i32);
+
+        gl.disable(WebGl2RenderingContext::CULL_FACE);
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, self.vertex_count);
+        gl.bind_vertex_array(None);
+        gl.enable(WebGl2RenderingContext::CULL_FACE);
+    }
+}
@@ -0,0 +1,509 @@
+pub type Mat4 = [f32; 16];
+pub type Vec2 = [f32; 2];
+pub type Vec3 = [f32; 3];
+
+pub fn to_mat4(m: &[f32]) -> Mat4 {
+    let mut out = [0.; 16];
+    out.copy_from_slice(&m[..16]);
+    out
+}
+
+pub fn multiply(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut out = [0.; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+pub const IDENTITY: Mat4 = [
+    1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1., 0., 0., 0., 0., 1.,
+];
+
+pub fn translation(x: f32, y: f32, z: f32) -> Mat4 {
+    let mut out = IDENTITY;
+    out[12] = x;
+    out[13] = y;
+    out[14] = z;
+    out
+}
+
+pub fn scaling(x: f32, y: f32, z: f32) -> Mat4 {
+    let mut out = IDENTITY;
+    out[0] = x;
+    out[5] = y;
+    out[10] = z;
+    out
+}
+
+// Rotation from a quaternion (x, y, z, w), as in XRRigidTransform.orientation. The quaternion
+// is normalized first; a zero quaternion gives the identity.
+pub fn rotation_quat(x: f32, y: f32, z: f32, w: f32) -> Mat4 {
+    let len = (x * x + y * y + z * z + w * w).sqrt();
+    if len == 0. {
+        return IDENTITY;
+    }
+    let (x, y, z, w) = (x / len, y / len, z / len, w / len);
+    [
+        1. - 2. * (y * y + z * z),
+        2. * (x * y + z * w),
+        2. * (x * z - y * w),
+        0.,
+        2. * (x * y - z * w),
+        1. - 2. * (x * x + z * z),
+        2. * (y * z + x * w),
+        0.,
+        2. * (x * z + y * w),
+        2. * (y * z - x * w),
+        1. - 2. * (x * x + y * y),
+        0.,
+        0.,
+        0.,
+        0.,
+        1.,
+    ]
+}
+
+// Quaternion (x, y, z, w) of the rotation part of a rigid transform, the inverse of
+// `rotation_quat`. Columns are normalized so a uniform scale doesn't skew the result.
+pub fn quat_from_mat4(m: &Mat4) -> [f32; 4] {
+    let column = |col: usize| normalize(&[m[col * 4], m[col * 4 + 1], m[col * 4 + 2]]);
+    let (x_axis, y_axis, z_axis) = (column(0), column(1), column(2));
+    // r(row, col) of the rotation matrix.
+    let r = |row: usize, col: usize| [x_axis, y_axis, z_axis][col][row];
+    let trace = r(0, 0) + r(1, 1) + r(2, 2);
+    if trace > 0. {
+        let s = (trace + 1.).sqrt() * 2.;
+        [
+            (r(2, 1) - r(1, 2)) / s,
+            (r(0, 2) - r(2, 0)) / s,
+            (r(1, 0) - r(0, 1)) / s,
+            s / 4.,
+        ]
+    } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+        let s = (1. + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * 2.;
+        [
+            s / 4.,
+            (r(0, 1) + r(1, 0)) / s,
+            (r(0, 2) + r(2, 0)) / s,
+            (r(2, 1) - r(1, 2)) / s,
+        ]
+    } else if r(1, 1) > r(2, 2) {
+        let s = (1. + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * 2.;
+        [
+            (r(0, 1) + r(1, 0)) / s,
+            s / 4.,
+            (r(1, 2) + r(2, 1)) / s,
+            (r(0, 2) - r(2, 0)) / s,
+        ]
+    } else {
+        let s = (1. + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * 2.;
+        [
+            (r(0, 2) + r(2, 0)) / s,
+            (r(1, 2) + r(2, 1)) / s,
+            s / 4.,
+            (r(1, 0) - r(0, 1)) / s,
+        ]
+    }
+}
+
+pub fn rotation_x(angle: f32) -> Mat4 {
+    let (sin, cos) = angle.sin_cos();
+    [
+        1., 0., 0., 0., 0., cos, sin, 0., 0., -sin, cos, 0., 0., 0., 0., 1.,
+    ]
+}
+
+// Negates the output X of `m`, mirroring what it renders horizontally. Mirroring reverses
+// triangle winding, so the front face has to be flipped with it.
+pub fn mirror_x(m: &Mat4) -> Mat4 {
+    let mut out = *m;
+    for col in 0..4 {
+        out[col * 4] = -out[col * 4];
+    }
+    out
+}
+
+pub fn invert(m: &Mat4) -> Option<Mat4> {
+    let mut inv = [0.; 16];
+
+    inv[0] = m[5] * m[10] * m[15] - m[5] * m[11] * m[14] - m[9] * m[6] * m[15]
+        + m[9] * m[7] * m[14]
+        + m[13] * m[6] * m[11]
+        - m[13] * m[7] * m[10];
+    inv[4] = -m[4] * m[10] * m[15] + m[4] * m[11] * m[14] + m[8] * m[6] * m[15]
+        - m[8] * m[7] * m[14]
+        - m[12] * m[6] * m[11]
+        + m[12] * m[7] * m[10];
+    inv[8] = m[4] * m[9] * m[15] - m[4] * m[11] * m[13] - m[8] * m[5] * m[15]
+        + m[8] * m[7] * m[13]
+        + m[12] * m[5] * m[11]
+        - m[12] * m[7] * m[9];
+    inv[12] = -m[4] * m[9] * m[14] + m[4] * m[10] * m[13] + m[8] * m[5] * m[14]
+        - m[8] * m[6] * m[13]
+        - m[12] * m[5] * m[10]
+        + m[12] * m[6] * m[9];
+    inv[1] = -m[1] * m[10] * m[15] + m[1] * m[11] * m[14] + m[9] * m[2] * m[15]
+        - m[9] * m[3] * m[14]
+        - m[13] * m[2] * m[11]
+        + m[13] * m[3] * m[10];
+    inv[5] = m[0] * m[10] * m[15] - m[0] * m[11] * m[14] - m[8] * m[2] * m[15]
+        + m[8] * m[3] * m[14]
+        + m[12] * m[2] * m[11]
+        - m[12] * m[3] * m[10];
+    inv[9] = -m[0] * m[9] * m[15] + m[0] * m[11] * m[13] + m[8] * m[1] * m[15]
+        - m[8] * m[3] * m[13]
+        - m[12] * m[1] * m[11]
+        + m[12] * m[3] * m[9];
+    inv[13] = m[0] * m[9] * m[14] - m[0] * m[10] * m[13] - m[8] * m[1] * m[14]
+        + m[8] * m[2] * m[13]
+        + m[12] * m[1] * m[10]
+        - m[12] * m[2] * m[9];
+    inv[2] = m[1] * m[6] * m[15] - m[1] * m[7] * m[14] - m[5] * m[2] * m[15]
+        + m[5] * m[3] * m[14]
+        + m[13] * m[2] * m[7]
+        - m[13] * m[3] * m[6];
+    inv[6] = -m[0] * m[6] * m[15] + m[0] * m[7] * m[14] + m[4] * m[2] * m[15]
+        - m[4] * m[3] * m[14]
+        - m[12] * m[2] * m[7]
+        + m[12] * m[3] * m[6];
+    inv[10] = m[0] * m[5] * m[15] - m[0] * m[7] * m[13] - m[4] * m[1] * m[15]
+        + m[4] * m[3] * m[13]
+        + m[12] * m[1] * m[7]
+        - m[12] * m[3] * m[5];
+    inv[14] = -m[0] * m[5] * m[14] + m[0] * m[6] * m[13] + m[4] * m[1] * m[14]
+        - m[4] * m[2] * m[13]
+        - m[12] * m[1] * m[6]
+        + m[12] * m[2] * m[5];
+    inv[3] = -m[1] * m[6] * m[11] + m[1] * m[7] * m[10] + m[5] * m[2] * m[11]
+        - m[5] * m[3] * m[10]
+        - m[9] * m[2] * m[7]
+        + m[9] * m[3] * m[6];
+    inv[7] = m[0] * m[6] * m[11] - m[0] * m[7] * m[10] - m[4] * m[2] * m[11]
+        + m[4] * m[3] * m[10]
+        + m[8] * m[2] * m[7]
+        - m[8] * m[3] * m[6];
+    inv[11] = -m[0] * m[5] * m[11] + m[0] * m[7] * m[9] + m[4] * m[1] * m[11]
+        - m[4] * m[3] * m[9]
+        - m[8] * m[1] * m[7]
+        + m[8] * m[3] * m[5];
+    inv[15] = m[0] * m[5] * m[10] - m[0] * m[6] * m[9] - m[4] * m[1] * m[10]
+        + m[4] * m[2] * m[9]
+        + m[8] * m[1] * m[6]
+        - m[8] * m[2] * m[5];
+
+    let det = m[0] * inv[0] + m[1] * inv[4] + m[2] * inv[8] + m[3] * inv[12];
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    for v in inv.iter_mut() {
+        *v *= inv_det;
+    }
+    Some(inv)
+}
+
+pub fn transform_point(m: &Mat4, p: &Vec3) -> Vec3 {
+    let x = m[0] * p[0] + m[4] * p[1] + m[8] * p[2] + m[12];
+    let y = m[1] * p[0] + m[5] * p[1] + m[9] * p[2] + m[13];
+    let z = m[2] * p[0] + m[6] * p[1] + m[10] * p[2] + m[14];
+    let w = m[3] * p[0] + m[7] * p[1] + m[11] * p[2] + m[15];
+    [x / w, y / w, z / w]
+}
+
+pub fn add(a: &Vec3, b: &Vec3) -> Vec3 {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: &Vec3, b: &Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(v: &Vec3, s: f32) -> Vec3 {
+    [v[0] * s, v[1] * s, v[2] * s]
+}
+
+pub fn dot(a: &Vec3, b: &Vec3) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn length(v: &Vec3) -> f32 {
+    (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt()
+}
+
+pub fn normalize(v: &Vec3) -> Vec3 {
+    let len = length(v);
+    if len == 0. {
+        return *v;
+    }
+    [v[0] / len, v[1] / len, v[2] / len]
+}
+
+// Plane [nx, ny, nz, d] with a unit normal, for points where dot(normal, p) = d.
+pub fn plane(normal: &Vec3, distance: f32) -> [f32; 4] {
+    let [x, y, z] = normalize(normal);
+    [x, y, z, distance]
+}
+
+// x and y are normalized screen coordinates in [0, 1] with the origin at the top left.
+pub fn screen_to_ray(view: &Mat4, projection: &Mat4, x: f32, y: f32) -> Option<(Vec3, Vec3)> {
+    let inv_view_projection = invert(&multiply(projection, view))?;
+    let ndc_x = x * 2. - 1.;
+    let ndc_y = 1. - y * 2.;
+
+    let near = transform_point(&inv_view_projection, &[ndc_x, ndc_y, -1.]);
+    let far = transform_point(&inv_view_projection, &[ndc_x, ndc_y, 1.]);
+    Some((near, normalize(&sub(&far, &near))))
+}
+
+// Rotation around the Y axis of the matrix's -Z (forward) axis projected onto the horizontal plane.
+pub fn yaw(m: &Mat4) -> f32 {
+    let forward_x = -m[8];
+    let forward_z = -m[10];
+    if forward_x.abs() < f32::EPSILON && forward_z.abs() < f32::EPSILON {
+        return 0.;
+    }
+    (-forward_x).atan2(-forward_z)
+}
+
+pub fn rotate_y(v: &Vec3, angle: f32) -> Vec3 {
+    let (sin, cos) = angle.sin_cos();
+    [v[0] * cos + v[2] * sin, v[1], -v[0] * sin + v[2] * cos]
+}
+
+// Offset (horizontal position, yaw) for a reference space so that `spawn` ends up at `head`,
+// ignoring pitch, roll and height.
+pub fn yaw_alignment_offset(head: &Mat4, spawn: &Mat4) -> (Vec3, f32) {
+    let angle = yaw(head) - yaw(spawn);
+    let spawn_position = rotate_y(&[spawn[12], 0., spawn[14]], angle);
+    let position = [
+        head[12] - spawn_position[0],
+        0.,
+        head[14] - spawn_position[2],
+    ];
+    (position, angle)
+}
+
+// Rotation from `from` to `to` as an axis scaled by the angle in radians.
+pub fn rotation_delta(from: &Mat4, to: &Mat4) -> Vec3 {
+    // to * from^T using only the rotation parts; r(row, col).
+    let r = |row: usize, col: usize| -> f32 {
+        (0..3).map(|k| to[k * 4 + row] * from[k * 4 + col]).sum()
+    };
+    let trace = r(0, 0) + r(1, 1) + r(2, 2);
+    let angle = ((trace - 1.) / 2.).clamp(-1., 1.).acos();
+    let axis = [r(2, 1) - r(1, 2), r(0, 2) - r(2, 0), r(1, 0) - r(0, 1)];
+    let sin = angle.sin();
+    if sin.abs() < f32::EPSILON {
+        return [0., 0., 0.];
+    }
+    scale(&axis, angle / (2. * sin))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    // Symmetric perspective with a 90 degree field of view, near 1 and far 100.
+    fn perspective() -> Mat4 {
+        let (near, far) = (1., 100.);
+        [
+            1.,
+            0.,
+            0.,
+            0.,
+            0.,
+            1.,
+            0.,
+            0.,
+            0.,
+            0.,
+            (far + near) / (near - far),
+            -1.,
+            0.,
+            0.,
+            2. * far * near / (near - far),
+            0.,
+        ]
+    }
+
+    fn rotation_y(angle: f32) -> Mat4 {
+        rotation_quat(0., (angle / 2.).sin(), 0., (angle / 2.).cos())
+    }
+
+    #[test]
+    fn multiply_applies_the_right_matrix_first() {
+        let (t, s) = (translation(1., 2., 3.), scaling(2., 2., 2.));
+        assert_close(
+            &transform_point(&multiply(&t, &s), &[1., 1., 1.]),
+            &[3., 4., 5.],
+        );
+        assert_close(
+            &transform_point(&multiply(&s, &t), &[1., 1., 1.]),
+            &[4., 6., 8.],
+        );
+    }
+
+    #[test]
+    fn multiply_by_identity_is_unchanged() {
+        let m = multiply(&translation(1., 2., 3.), &rotation_x(0.3));
+        assert_eq!(multiply(&m, &IDENTITY), m);
+        assert_eq!(multiply(&IDENTITY, &m), m);
+    }
+
+    #[test]
+    fn rotation_x_turns_up_toward_positive_z() {
+        let rotation = rotation_x(std::f32::consts::FRAC_PI_2);
+        assert_close(&transform_point(&rotation, &[0., 1., 0.]), &[0., 0., 1.]);
+        assert_close(&transform_point(&rotation, &[1., 0., 0.]), &[1., 0., 0.]);
+    }
+
+    #[test]
+    fn yaw_ignores_pitch_and_position() {
+        assert_eq!(yaw(&IDENTITY), 0.);
+        assert!((yaw(&rotation_y(0.7)) - 0.7).abs() < 1e-5);
+        let pitched = multiply(
+            &translation(4., 5., 6.),
+            &multiply(&rotation_y(-1.2), &rotation_x(0.4)),
+        );
+        assert!((yaw(&pitched) + 1.2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn yaw_of_a_vertical_forward_axis_is_zero() {
+        assert_eq!(yaw(&rotation_x(std::f32::consts::FRAC_PI_2)), 0.);
+    }
+
+    #[test]
+    fn alignment_offset_moves_spawn_onto_head() {
+        let head = multiply(&translation(1., 1.6, -2.), &rotation_y(0.5));
+        let spawn = multiply(&translation(-3., 0., 4.), &rotation_y(-0.25));
+        let (position, angle) = yaw_alignment_offset(&head, &spawn);
+        assert_eq!(position[1], 0.);
+        let offset = multiply(
+            &translation(position[0], position[1], position[2]),
+            &rotation_y(angle),
+        );
+        let aligned = multiply(&offset, &spawn);
+        assert_close(&[aligned[12], aligned[14]], &[head[12], head[14]]);
+        assert!((yaw(&aligned) - yaw(&head)).abs() < 1e-5);
+        // Height is left alone.
+        assert_eq!(aligned[13], spawn[13]);
+    }
+
+    #[test]
+    fn rotation_delta_is_axis_times_angle() {
+        assert_close(&rotation_delta(&IDENTITY, &rotation_x(0.5)), &[0.5, 0., 0.]);
+        assert_close(
+            &rotation_delta(&rotation_y(0.3), &rotation_y(0.5)),
+            &[0., 0.2, 0.],
+        );
+        assert_close(
+            &rotation_delta(&rotation_y(0.5), &rotation_y(0.3)),
+            &[0., -0.2, 0.],
+        );
+    }
+
+    #[test]
+    fn rotation_delta_ignores_translation() {
+        let from = translation(1., 2., 3.);
+        assert_eq!(
+            rotation_delta(&from, &translation(4., 5., 6.)),
+            [0., 0., 0.]
+        );
+        let to = multiply(&translation(4., 5., 6.), &rotation_x(0.5));
+        assert_close(&rotation_delta(&from, &to), &[0.5, 0., 0.]);
+    }
+
+    #[test]
+    fn plane_normal_is_normalized() {
+        assert_close(&plane(&[0., 2., 0.], 1.5), &[0., 1., 0., 1.5]);
+    }
+
+    #[test]
+    fn plane_passes_through_the_normal_at_its_distance() {
+        let [x, y, z, d] = plane(&[1., 1., 0.], 2.);
+        let normal = [x, y, z];
+        assert!((dot(&normal, &scale(&normal, 2.)) - d).abs() < 1e-6);
+        // Any point moved along the plane stays on it.
+        let along = add(&scale(&normal, 2.), &[0., 0., 7.]);
+        assert!((dot(&normal, &along) - d).abs() < 1e-6);
+    }
+
+    #[test]
+    fn plane_normal_points_to_the_kept_side() {
+        // x = 2, keeping x > 2.
+        let [x, y, z, d] = plane(&[1., 0., 0.], 2.);
+        assert!(dot(&[x, y, z], &[3., 0., 0.]) > d);
+        assert!(dot(&[x, y, z], &[1., 5., 5.]) < d);
+        // Flipping the normal and distance keeps the other side.
+        let [x, y, z, d] = plane(&[-1., 0., 0.], -2.);
+        assert!(dot(&[x, y, z], &[3., 0., 0.]) < d);
+        assert!(dot(&[x, y, z], &[1., 5., 5.]) > d);
+    }
+
+    #[test]
+    fn screen_center_looks_down_negative_z() {
+        let (origin, direction) = screen_to_ray(&IDENTITY, &perspective(), 0.5, 0.5).unwrap();
+        assert_close(&origin, &[0., 0., -1.]);
+        assert_close(&direction, &[0., 0., -1.]);
+    }
+
+    #[test]
+    fn screen_corners_follow_the_field_of_view() {
+        let (origin, direction) = screen_to_ray(&IDENTITY, &perspective(), 1., 0.).unwrap();
+        assert_close(&origin, &[1., 1., -1.]);
+        assert_close(&direction, &normalize(&[1., 1., -1.]));
+    }
+
+    #[test]
+    fn screen_ray_starts_from_the_camera_position() {
+        // The view matrix is the inverse of a camera at (0, 0, 5).
+        let view = translation(0., 0., -5.);
+        let (origin, direction) = screen_to_ray(&view, &perspective(), 0.5, 0.5).unwrap();
+        assert_close(&origin, &[0., 0., 4.]);
+        assert_close(&direction, &[0., 0., -1.]);
+    }
+
+    #[test]
+    fn screen_ray_needs_an_invertible_projection() {
+        assert!(screen_to_ray(&IDENTITY, &[0.; 16], 0.5, 0.5).is_none());
+    }
+
+    #[test]
+    fn mirror_x_flips_the_projected_x_only() {
+        let projection = perspective();
+        let mirrored = mirror_x(&projection);
+        let point = [0.5, 0.25, -2.];
+        let [x, y, z] = transform_point(&projection, &point);
+        assert_close(&transform_point(&mirrored, &point), &[-x, y, z]);
+    }
+
+    #[test]
+    fn mirror_x_twice_is_the_original() {
+        let m = multiply(&perspective(), &translation(1., 2., 3.));
+        assert_eq!(mirror_x(&mirror_x(&m)), m);
+    }
+
+    #[test]
+    fn mirror_x_reverses_winding() {
+        // A counter-clockwise triangle on screen turns clockwise.
+        let winding = |m: &Mat4| {
+            let [a, b, c] =
+                [[0., 0., -2.], [1., 0., -2.], [0., 1., -2.]].map(|p| transform_point(m, &p));
+            (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+        };
+        assert!(winding(&perspective()) > 0.);
+        assert!(winding(&mirror_x(&perspective())) < 0.);
+    }
+}
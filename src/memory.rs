@@ -0,0 +1,165 @@
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlTexture};
+
+// Approximate GPU memory use, updated on every buffer and texture upload.
+#[derive(Default)]
+pub struct GpuMemory {
+    buffers: Vec<(WebGlBuffer, u64)>,
+    textures: Vec<(WebGlTexture, u64)>,
+    pub budget: Option<u64>,
+}
+
+pub fn buffer_bytes(element_count: usize, element_size: usize) -> u64 {
+    (element_count * element_size) as u64
+}
+
+// Size of a `width` by `height` texture with `layers` layers and no mipmaps. Drivers usually
+// pad 24-bit formats to 32 bits, so those count 4 bytes per texel, as do unknown formats.
+pub fn texture_bytes(width: i32, height: i32, layers: i32, format: u32) -> u64 {
+    let texel_bytes = match format {
+        WebGl2RenderingContext::R8
+        | WebGl2RenderingContext::LUMINANCE
+        | WebGl2RenderingContext::ALPHA => 1,
+        WebGl2RenderingContext::RG8
+        | WebGl2RenderingContext::LUMINANCE_ALPHA
+        | WebGl2RenderingContext::DEPTH_COMPONENT16 => 2,
+        WebGl2RenderingContext::RGBA16F => 8,
+        WebGl2RenderingContext::RGBA32F => 16,
+        _ => 4,
+    };
+    let texels = width.max(0) as u64 * height.max(0) as u64 * layers.max(0) as u64;
+    texels * texel_bytes
+}
+
+impl GpuMemory {
+    pub fn buffer_data(&mut self, buffer: &WebGlBuffer, bytes: u64) {
+        match self.buffers.iter_mut().find(|(b, _)| b == buffer) {
+            Some(entry) => entry.1 = bytes,
+            None => self.buffers.push((buffer.clone(), bytes)),
+        }
+        self.check_budget();
+    }
+
+    pub fn texture_data(&mut self, texture: &WebGlTexture, bytes: u64) {
+        match self.textures.iter_mut().find(|(t, _)| t == texture) {
+            Some(entry) => entry.1 = bytes,
+            None => self.textures.push((texture.clone(), bytes)),
+        }
+        self.check_budget();
+    }
+
+    fn check_budget(&self) {
+        if let Some(budget) = self.budget {
+            let total = self.total();
+            if total > budget {
+                log!(
+                    "estimated GPU memory {} bytes exceeds budget {} bytes",
+                    total,
+                    budget
+                );
+            }
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        let buffers: u64 = self.buffers.iter().map(|(_, bytes)| bytes).sum();
+        let textures: u64 = self.textures.iter().map(|(_, bytes)| bytes).sum();
+        buffers + textures
+    }
+
+    // Deletes a buffer and stops counting it.
+    pub fn delete(&mut self, gl: &WebGl2RenderingContext, buffer: &WebGlBuffer) {
+        self.buffers.retain(|(b, _)| b != buffer);
+        gl.delete_buffer(Some(buffer));
+    }
+
+    // Deletes a texture and stops counting it.
+    pub fn delete_texture(&mut self, gl: &WebGl2RenderingContext, texture: &WebGlTexture) {
+        self.textures.retain(|(t, _)| t != texture);
+        gl.delete_texture(Some(texture));
+    }
+
+    // Deletes every tracked buffer and texture and forgets it.
+    pub fn delete_all(&mut self, gl: &WebGl2RenderingContext) {
+        for (buffer, _) in self.buffers.drain(..) {
+            gl.delete_buffer(Some(&buffer));
+        }
+        for (texture, _) in self.textures.drain(..) {
+            gl.delete_texture(Some(&texture));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_bytes_count_every_texel_of_every_layer() {
+        assert_eq!(
+            texture_bytes(1024, 512, 2, WebGl2RenderingContext::RGBA8),
+            1024 * 512 * 2 * 4
+        );
+        assert_eq!(
+            texture_bytes(160, 90, 1, WebGl2RenderingContext::LUMINANCE_ALPHA),
+            160 * 90 * 2
+        );
+        assert_eq!(
+            texture_bytes(16, 16, 1, WebGl2RenderingContext::RGBA32F),
+            16 * 16 * 16
+        );
+    }
+
+    #[test]
+    fn texture_bytes_pad_24_bit_depth_to_32_bits() {
+        assert_eq!(
+            texture_bytes(100, 100, 2, WebGl2RenderingContext::DEPTH_COMPONENT24),
+            texture_bytes(100, 100, 2, WebGl2RenderingContext::RGBA8)
+        );
+        assert_eq!(
+            texture_bytes(100, 100, 1, WebGl2RenderingContext::DEPTH_COMPONENT16),
+            100 * 100 * 2
+        );
+    }
+
+    #[test]
+    fn empty_or_negative_sizes_take_no_memory() {
+        assert_eq!(texture_bytes(0, 512, 2, WebGl2RenderingContext::RGBA8), 0);
+        assert_eq!(texture_bytes(-1, 512, 2, WebGl2RenderingContext::RGBA8), 0);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn deleted_buffers_are_no_longer_counted() {
+        let gl = crate::create_webgl_context(false, None).unwrap();
+        let mut gpu_memory = GpuMemory::default();
+        let kept = gl.create_buffer().unwrap();
+        let deleted = gl.create_buffer().unwrap();
+        gpu_memory.buffer_data(&kept, 64);
+        gpu_memory.buffer_data(&deleted, 32);
+        gpu_memory.delete(&gl, &deleted);
+        assert_eq!(gpu_memory.total(), 64);
+        assert!(!gl.is_buffer(Some(&deleted)));
+    }
+
+    #[wasm_bindgen_test]
+    fn textures_are_counted_until_deleted() {
+        let gl = crate::create_webgl_context(false, None).unwrap();
+        let mut gpu_memory = GpuMemory::default();
+        let buffer = gl.create_buffer().unwrap();
+        let texture = gl.create_texture().unwrap();
+        gpu_memory.buffer_data(&buffer, 64);
+        gpu_memory.texture_data(&texture, 256);
+        gpu_memory.texture_data(&texture, 128);
+        assert_eq!(gpu_memory.total(), 192);
+        gpu_memory.delete_texture(&gl, &texture);
+        assert_eq!(gpu_memory.total(), 64);
+        assert!(!gl.is_texture(Some(&texture)));
+    }
+}
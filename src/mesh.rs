@@ -0,0 +1,205 @@
+use crate::math::{self, Mat4};
+use crate::memory::{self, GpuMemory};
+use crate::shader::UniformCache;
+use web_sys::*;
+
+// position(3) color(3), matching the main program's vertexPosition and vertexColor.
+const FLOATS_PER_VERTEX: usize = 6;
+
+pub struct Mesh {
+    vertices: Vec<f32>,
+    indices: Vec<u16>,
+    pub model: Mat4,
+}
+
+impl Mesh {
+    pub fn new(vertices: &[f32], indices: &[u16]) -> Result<Mesh, String> {
+        if !vertices.len().is_multiple_of(FLOATS_PER_VERTEX) {
+            return Err(format!(
+                "vertices must have {} floats per vertex (position, color)",
+                FLOATS_PER_VERTEX
+            ));
+        }
+        if !indices.len().is_multiple_of(3) {
+            return Err(String::from("indices must describe whole triangles"));
+        }
+        let vertex_count = vertices.len() / FLOATS_PER_VERTEX;
+        if let Some(index) = indices
+            .iter()
+            .find(|&&index| index as usize >= vertex_count)
+        {
+            return Err(format!(
+                "index {} is out of range for {} vertices",
+                index, vertex_count
+            ));
+        }
+        Ok(Mesh {
+            vertices: vertices.to_vec(),
+            indices: indices.to_vec(),
+            model: math::IDENTITY,
+        })
+    }
+}
+
+// Meshes are only ever appended, so the renderer uploads the ones past what it already has.
+#[derive(Default)]
+pub struct Meshes {
+    meshes: Vec<Mesh>,
+}
+
+impl Meshes {
+    pub fn add(&mut self, mesh: Mesh) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut Mesh> {
+        self.meshes.get_mut(id)
+    }
+}
+
+struct GpuMesh {
+    vao: WebGlVertexArrayObject,
+    vertex_buffer: WebGlBuffer,
+    index_buffer: WebGlBuffer,
+    index_count: i32,
+    model: Mat4,
+}
+
+pub struct MeshRenderer {
+    uploaded: Vec<GpuMesh>,
+    position_location: i32,
+    color_location: i32,
+    model_location: Option<WebGlUniformLocation>,
+}
+
+impl MeshRenderer {
+    pub fn new(gl: &WebGl2RenderingContext, program: &WebGlProgram) -> MeshRenderer {
+        MeshRenderer {
+            uploaded: Vec::new(),
+            position_location: gl.get_attrib_location(program, "vertexPosition"),
+            color_location: gl.get_attrib_location(program, "vertexColor"),
+            model_location: gl.get_uniform_location(program, "model"),
+        }
+    }
+
+    // Vertex position and color attribute locations in the main program.
+    pub fn attribute_locations(&self) -> (i32, i32) {
+        (self.position_location, self.color_location)
+    }
+
+    pub fn sync(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        meshes: &Meshes,
+        gpu_memory: &mut GpuMemory,
+    ) -> Result<(), String> {
+        for (uploaded, mesh) in self.uploaded.iter_mut().zip(&meshes.meshes) {
+            uploaded.model = mesh.model;
+        }
+        for mesh in &meshes.meshes[self.uploaded.len()..] {
+            let gpu_mesh = self.upload(gl, mesh, gpu_memory)?;
+            self.uploaded.push(gpu_mesh);
+        }
+        Ok(())
+    }
+
+    fn upload(
+        &self,
+        gl: &WebGl2RenderingContext,
+        mesh: &Mesh,
+        gpu_memory: &mut GpuMemory,
+    ) -> Result<GpuMesh, String> {
+        let vao = gl
+            .create_vertex_array()
+            .ok_or_else(|| String::from("unable to create vertex array"))?;
+        let vertex_buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("unable to create buffer"))?;
+        let index_buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("unable to create buffer"))?;
+        gl.bind_vertex_array(Some(&vao));
+
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&vertex_buffer));
+        unsafe {
+            let vertices = js_sys::Float32Array::view(&mesh.vertices);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vertices,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+        gpu_memory.buffer_data(&vertex_buffer, memory::buffer_bytes(mesh.vertices.len(), 4));
+
+        gl.bind_buffer(
+            WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+            Some(&index_buffer),
+        );
+        unsafe {
+            let indices = js_sys::Uint16Array::view(&mesh.indices);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER,
+                &indices,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+        gpu_memory.buffer_data(&index_buffer, memory::buffer_bytes(mesh.indices.len(), 2));
+
+        let stride = (FLOATS_PER_VERTEX * 4) as i32;
+        for (location, offset) in [(self.position_location, 0), (self.color_location, 3)].iter() {
+            if *location < 0 {
+                continue;
+            }
+            gl.enable_vertex_attrib_array(*location as u32);
+            gl.vertex_attrib_pointer_with_i32(
+                *location as u32,
+                3,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                stride,
+                offset * 4,
+            );
+        }
+        gl.bind_vertex_array(None);
+
+        Ok(GpuMesh {
+            vao,
+            vertex_buffer,
+            index_buffer,
+            index_count: mesh.indices.len() as i32,
+            model: mesh.model,
+        })
+    }
+
+    // Deletes every uploaded mesh. A new renderer uploads them again from `Meshes`.
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        for mesh in self.uploaded {
+            gl.delete_vertex_array(Some(&mesh.vao));
+            gpu_memory.delete(gl, &mesh.vertex_buffer);
+            gpu_memory.delete(gl, &mesh.index_buffer);
+        }
+    }
+
+    // Expects the main program to be in use.
+    pub fn draw(&self, gl: &WebGl2RenderingContext, uniforms: &mut UniformCache) {
+        self.draw_with(gl, |model| {
+            uniforms.uniform_matrix4fv(gl, "model", self.model_location.as_ref(), model)
+        });
+    }
+
+    // Draws every mesh with the program in use, calling `set_model` with each model matrix.
+    pub fn draw_with<F: FnMut(&Mat4)>(&self, gl: &WebGl2RenderingContext, mut set_model: F) {
+        for mesh in &self.uploaded {
+            set_model(&mesh.model);
+            gl.bind_vertex_array(Some(&mesh.vao));
+            gl.draw_elements_with_i32(
+                WebGl2RenderingContext::TRIANGLES,
+                mesh.index_count,
+                WebGl2RenderingContext::UNSIGNED_SHORT,
+                0,
+            );
+        }
+        gl.bind_vertex_array(None);
+    }
+}
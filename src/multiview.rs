@@ -0,0 +1,354 @@
+use crate::memory::{self, GpuMemory};
+use crate::mesh::MeshRenderer;
+use crate::shader::ProgramCache;
+use crate::viewport::Viewport;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // OVR_multiview2 and OCULUS_multiview aren't in web-sys yet. OCULUS_multiview has every
+    // method of OVR_multiview2, so either can be used as one.
+    type OvrMultiview2;
+
+    #[wasm_bindgen(method, js_name = framebufferTextureMultiviewOVR)]
+    fn framebuffer_texture_multiview_ovr(
+        this: &OvrMultiview2,
+        target: u32,
+        attachment: u32,
+        texture: Option<&WebGlTexture>,
+        level: i32,
+        base_view_index: i32,
+        num_views: i32,
+    );
+
+    // OCULUS_multiview only. The texture array stays single-sampled and is resolved to
+    // implicitly after drawing.
+    #[wasm_bindgen(method, js_name = framebufferTextureMultisampleMultiviewOVR)]
+    fn framebuffer_texture_multisample_multiview_ovr(
+        this: &OvrMultiview2,
+        target: u32,
+        attachment: u32,
+        texture: Option<&WebGlTexture>,
+        level: i32,
+        samples: i32,
+        base_view_index: i32,
+        num_views: i32,
+    );
+}
+
+pub const VIEW_COUNT: usize = 2;
+
+// Samples per pixel for the multisampled multiview pass, capped by the driver's maximum.
+const SAMPLES: i32 = 4;
+
+// The main vertex shader drawing both eyes in one pass, picking each eye's matrices with
+// gl_ViewID_OVR. Attribute locations are pinned to the main program's so the mesh vertex
+// arrays work with either program.
+fn vertex_shader(position_location: i32, color_location: i32) -> String {
+    format!(
+        "#version 300 es
+#extension GL_OVR_multiview2 : require
+layout(num_views = {}) in;
+uniform mat4 model;
+uniform mat4 views[{}];
+uniform mat4 projections[{}];
+layout(location = {}) in vec3 vertexPosition;
+layout(location = {}) in vec3 vertexColor;
+out vec3 vColor;
+out vec3 vWorldPosition;
+out float vViewDepth;
+void main() {{
+    vColor = vertexColor;
+    vec4 worldPosition = model * vec4(vertexPosition, 1.0);
+    vWorldPosition = worldPosition.xyz;
+    vec4 viewPosition = views[gl_ViewID_OVR] * worldPosition;
+    vViewDepth = -viewPosition.z;
+    gl_Position = projections[gl_ViewID_OVR] * viewPosition;
+}}",
+        VIEW_COUNT, VIEW_COUNT, VIEW_COUNT, position_location, color_location
+    )
+}
+
+// Renders meshes for both eyes into the layers of a texture array in a single pass, then
+// copies each layer into its eye's viewport of the XR layer. XRWebGLLayer framebuffers can't
+// be multiview targets themselves. With OCULUS_multiview the pass is multisampled; plain
+// OVR_multiview2 can't antialias, so it is only used for layers that don't either.
+pub struct Multiview {
+    extension: OvrMultiview2,
+    // 0 for a single-sampled pass.
+    samples: i32,
+    program: WebGlProgram,
+    // Both layers, for the multiview pass.
+    framebuffer: WebGlFramebuffer,
+    // One layer at a time, for drawing overlays and copying to the XR layer.
+    view_framebuffer: WebGlFramebuffer,
+    color: Option<WebGlTexture>,
+    depth: Option<WebGlTexture>,
+    size: Viewport,
+    model_location: Option<WebGlUniformLocation>,
+    views_location: Option<WebGlUniformLocation>,
+    projections_location: Option<WebGlUniformLocation>,
+    clip_enabled_location: Option<WebGlUniformLocation>,
+    clip_plane_location: Option<WebGlUniformLocation>,
+}
+
+impl Multiview {
+    // None when neither extension is supported, or when `antialias` is set and only
+    // OVR_multiview2 is.
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program_cache: &mut ProgramCache,
+        mesh_renderer: &MeshRenderer,
+        fragment_shader: &str,
+        antialias: bool,
+    ) -> Result<Option<Multiview>, String> {
+        let (extension, samples) = match gl.get_extension("OCULUS_multiview") {
+            Ok(Some(extension)) if antialias => {
+                let max_samples = gl
+                    .get_parameter(WebGl2RenderingContext::MAX_SAMPLES)
+                    .ok()
+                    .and_then(|max_samples| max_samples.as_f64())
+                    .unwrap_or(0.) as i32;
+                (extension, SAMPLES.min(max_samples))
+            }
+            Ok(Some(extension)) => (extension, 0),
+            _ if antialias => return Ok(None),
+            _ => match gl.get_extension("OVR_multiview2") {
+                Ok(Some(extension)) => (extension, 0),
+                _ => return Ok(None),
+            },
+        };
+        let extension = extension.unchecked_into::<OvrMultiview2>();
+        // The shaders use GL_OVR_multiview2 with either extension.
+        let _ = gl.get_extension("OVR_multiview2");
+        let (position_location, color_location) = mesh_renderer.attribute_locations();
+        let program = program_cache.get_or_link(
+            gl,
+            &vertex_shader(position_location, color_location),
+            fragment_shader,
+        )?;
+        let framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| String::from("unable to create framebuffer"))?;
+        let view_framebuffer = gl
+            .create_framebuffer()
+            .ok_or_else(|| String::from("unable to create framebuffer"))?;
+
+        Ok(Some(Multiview {
+            model_location: gl.get_uniform_location(&program, "model"),
+            views_location: gl.get_uniform_location(&program, "views"),
+            projections_location: gl.get_uniform_location(&program, "projections"),
+            clip_enabled_location: gl.get_uniform_location(&program, "clipEnabled"),
+            clip_plane_location: gl.get_uniform_location(&program, "clipPlane"),
+            extension,
+            samples,
+            program,
+            framebuffer,
+            view_framebuffer,
+            color: None,
+            depth: None,
+            size: Viewport {
+                x: 0,
+                y: 0,
+                width: 0,
+                height: 0,
+            },
+        }))
+    }
+
+    pub fn delete(mut self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_framebuffer(Some(&self.view_framebuffer));
+        self.delete_textures(gl, gpu_memory);
+    }
+
+    fn delete_textures(&mut self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        for texture in [self.color.take(), self.depth.take()].iter().flatten() {
+            gpu_memory.delete_texture(gl, texture);
+        }
+    }
+
+    fn resize(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        width: i32,
+        height: i32,
+        gpu_memory: &mut GpuMemory,
+    ) {
+        if self.size.width == width && self.size.height == height {
+            return;
+        }
+        self.delete_textures(gl, gpu_memory);
+        let (color, depth) = (
+            WebGl2RenderingContext::RGBA8,
+            WebGl2RenderingContext::DEPTH_COMPONENT24,
+        );
+        self.color = texture_array(gl, color, width, height, gpu_memory);
+        self.depth = texture_array(gl, depth, width, height, gpu_memory);
+        self.size.width = width;
+        self.size.height = height;
+
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+            Some(&self.framebuffer),
+        );
+        for (attachment, texture) in [
+            (WebGl2RenderingContext::COLOR_ATTACHMENT0, &self.color),
+            (WebGl2RenderingContext::DEPTH_ATTACHMENT, &self.depth),
+        ]
+        .iter()
+        {
+            if self.samples > 0 {
+                self.extension
+                    .framebuffer_texture_multisample_multiview_ovr(
+                        WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+                        *attachment,
+                        texture.as_ref(),
+                        0,
+                        self.samples,
+                        0,
+                        VIEW_COUNT as i32,
+                    );
+            } else {
+                self.extension.framebuffer_texture_multiview_ovr(
+                    WebGl2RenderingContext::DRAW_FRAMEBUFFER,
+                    *attachment,
+                    texture.as_ref(),
+                    0,
+                    0,
+                    VIEW_COUNT as i32,
+                );
+            }
+        }
+    }
+
+    // Clears both layers, sized to fit the largest camera viewport, and draws the meshes into
+    // them. Leaves the multiview program in use.
+    pub fn draw_meshes(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        mesh_renderer: &MeshRenderer,
+        cameras: &[(&[f32], &[f32], Viewport)],
+        clear_mask: u32,
+        clip_plane: Option<[f32; 4]>,
+        gpu_memory: &mut GpuMemory,
+    ) {
+        let width = cameras.iter().map(|(_, _, vp)| vp.width).max().unwrap_or(1);
+        let height = cameras
+            .iter()
+            .map(|(_, _, vp)| vp.height)
+            .max()
+            .unwrap_or(1);
+        self.resize(gl, width, height, gpu_memory);
+
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(&self.framebuffer));
+        gl.viewport(0, 0, width, height);
+        gl.clear(clear_mask);
+
+        let views: Vec<f32> = cameras
+            .iter()
+            .flat_map(|(view, _, _)| view.iter())
+            .copied()
+            .collect();
+        let projections: Vec<f32> = cameras
+            .iter()
+            .flat_map(|(_, projection, _)| projection.iter())
+            .copied()
+            .collect();
+        gl.use_program(Some(&self.program));
+        gl.uniform_matrix4fv_with_f32_array(self.views_location.as_ref(), false, &views);
+        gl.uniform_matrix4fv_with_f32_array(
+            self.projections_location.as_ref(),
+            false,
+            &projections,
+        );
+        gl.uniform1i(
+            self.clip_enabled_location.as_ref(),
+            clip_plane.is_some() as i32,
+        );
+        if let Some(plane) = clip_plane {
+            gl.uniform4fv_with_f32_array(self.clip_plane_location.as_ref(), &plane);
+        }
+        mesh_renderer.draw_with(gl, |model| {
+            gl.uniform_matrix4fv_with_f32_array(self.model_location.as_ref(), false, model);
+        });
+    }
+
+    // Binds layer `index` as an ordinary framebuffer, with depth from the multiview pass, for
+    // drawing overlays. Returns the viewport to draw them in.
+    pub fn bind_view(&self, gl: &WebGl2RenderingContext, index: usize) -> Viewport {
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            Some(&self.view_framebuffer),
+        );
+        gl.framebuffer_texture_layer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            WebGl2RenderingContext::COLOR_ATTACHMENT0,
+            self.color.as_ref(),
+            0,
+            index as i32,
+        );
+        gl.framebuffer_texture_layer(
+            WebGl2RenderingContext::FRAMEBUFFER,
+            WebGl2RenderingContext::DEPTH_ATTACHMENT,
+            self.depth.as_ref(),
+            0,
+            index as i32,
+        );
+        gl.viewport(0, 0, self.size.width, self.size.height);
+        self.size
+    }
+
+    // Copies the layer bound by `bind_view` into `viewport` of `target`, and leaves `target`
+    // bound.
+    pub fn blit_view(
+        &self,
+        gl: &WebGl2RenderingContext,
+        target: Option<&WebGlFramebuffer>,
+        viewport: &Viewport,
+    ) {
+        gl.bind_framebuffer(
+            WebGl2RenderingContext::READ_FRAMEBUFFER,
+            Some(&self.view_framebuffer),
+        );
+        gl.bind_framebuffer(WebGl2RenderingContext::DRAW_FRAMEBUFFER, target);
+        gl.blit_framebuffer(
+            0,
+            0,
+            self.size.width,
+            self.size.height,
+            viewport.x,
+            viewport.y,
+            viewport.x + viewport.width,
+            viewport.y + viewport.height,
+            WebGl2RenderingContext::COLOR_BUFFER_BIT,
+            WebGl2RenderingContext::LINEAR,
+        );
+        gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, target);
+    }
+}
+
+fn texture_array(
+    gl: &WebGl2RenderingContext,
+    format: u32,
+    width: i32,
+    height: i32,
+    gpu_memory: &mut GpuMemory,
+) -> Option<WebGlTexture> {
+    let texture = gl.create_texture()?;
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D_ARRAY, Some(&texture));
+    gl.tex_storage_3d(
+        WebGl2RenderingContext::TEXTURE_2D_ARRAY,
+        1,
+        format,
+        width,
+        height,
+        VIEW_COUNT as i32,
+    );
+    gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D_ARRAY, None);
+    let bytes = memory::texture_bytes(width, height, VIEW_COUNT as i32, format);
+    gpu_memory.texture_data(&texture, bytes);
+    Some(texture)
+}
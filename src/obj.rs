@@ -0,0 +1,253 @@
+use crate::convention::CoordinateConvention;
+use crate::math::Vec3;
+use std::collections::HashMap;
+
+// Meshes have no normal attribute, so normals are shown as colors, the absolute x, y and z
+// components becoming red, green and blue. Faces without normals are white.
+const NO_NORMAL_COLOR: Vec3 = [1., 1., 1.];
+
+// Resolves a 1-based, or negative relative, OBJ index into `count` elements.
+fn resolve_index(index: &str, count: usize, line_number: usize) -> Result<usize, String> {
+    let index: i64 = index
+        .parse()
+        .map_err(|_| format!("line {}: bad index {}", line_number, index))?;
+    let resolved = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    if resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "line {}: index {} is out of range",
+            line_number, index
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+fn parse_vec3<'a>(
+    mut values: impl Iterator<Item = &'a str>,
+    line_number: usize,
+) -> Result<Vec3, String> {
+    let mut vector = [0.; 3];
+    for component in vector.iter_mut() {
+        let value = values
+            .next()
+            .ok_or_else(|| format!("line {}: expected 3 values", line_number))?;
+        *component = value
+            .parse()
+            .map_err(|_| format!("line {}: bad number {}", line_number, value))?;
+    }
+    Ok(vector)
+}
+
+// Parses the `v`, `vn` and `f` lines of Wavefront OBJ text into interleaved position and
+// color vertices and triangle indices for `Mesh::new`. Polygons are split into triangle fans;
+// texture coordinates, groups and materials are ignored. Positions and normals are converted
+// from `convention` into WebXR's.
+pub fn parse(text: &str, convention: CoordinateConvention) -> Result<(Vec<f32>, Vec<u16>), String> {
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    // OBJ indexes positions and normals separately; each distinct pair becomes one vertex.
+    let mut vertex_ids: HashMap<(usize, Option<usize>), u16> = HashMap::new();
+
+    for (line_number, line) in text.lines().enumerate() {
+        let line_number = line_number + 1;
+        let mut values = line.split_whitespace();
+        match values.next() {
+            Some("v") => positions.push(convention.convert(&parse_vec3(values, line_number)?)),
+            Some("vn") => normals.push(convention.convert(&parse_vec3(values, line_number)?)),
+            Some("f") => {
+                let mut face = Vec::new();
+                for corner in values {
+                    let mut parts = corner.split('/');
+                    let position = resolve_index(
+                        parts.next().unwrap_or_default(),
+                        positions.len(),
+                        line_number,
+                    )?;
+                    let normal = match parts.nth(1) {
+                        Some(normal) if !normal.is_empty() => {
+                            Some(resolve_index(normal, normals.len(), line_number)?)
+                        }
+                        _ => None,
+                    };
+                    let id = match vertex_ids.get(&(position, normal)) {
+                        Some(id) => *id,
+                        None => {
+                            let id = vertex_ids.len();
+                            if id > u16::MAX as usize {
+                                return Err(format!(
+                                    "more than {} vertices",
+                                    u16::MAX as usize + 1
+                                ));
+                            }
+                            vertices.extend_from_slice(&positions[position]);
+                            let color = normal.map_or(NO_NORMAL_COLOR, |normal| {
+                                let [x, y, z] = normals[normal];
+                                [x.abs(), y.abs(), z.abs()]
+                            });
+                            vertices.extend_from_slice(&color);
+                            vertex_ids.insert((position, normal), id as u16);
+                            id as u16
+                        }
+                    };
+                    face.push(id);
+                }
+                if face.len() < 3 {
+                    return Err(format!(
+                        "line {}: a face needs at least 3 vertices",
+                        line_number
+                    ));
+                }
+                if convention.flips_winding() {
+                    face.reverse();
+                }
+                for i in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    if indices.is_empty() {
+        return Err(String::from("no faces found"));
+    }
+    Ok((vertices, indices))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    #[test]
+    fn triangle_without_normals_is_white() {
+        let (vertices, indices) = parse(TRIANGLE, CoordinateConvention::YUp).unwrap();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(
+            vertices,
+            vec![0., 0., 0., 1., 1., 1., 1., 0., 0., 1., 1., 1., 0., 1., 0., 1., 1., 1.]
+        );
+    }
+
+    #[test]
+    fn polygons_are_split_into_fans() {
+        let (_, indices) = parse(
+            "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n",
+            CoordinateConvention::YUp,
+        )
+        .unwrap();
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn normals_become_absolute_colors() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 -1\nf 1//1 2//1 3//1\n";
+        let (vertices, _) = parse(text, CoordinateConvention::YUp).unwrap();
+        for vertex in vertices.chunks_exact(6) {
+            assert_eq!(&vertex[3..], &[0., 0., 1.]);
+        }
+        // Texture coordinates between the slashes are skipped.
+        let text = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvn 1 0 0\nf 1/1/1 2/1/1 3/1/1\n";
+        let (vertices, _) = parse(text, CoordinateConvention::YUp).unwrap();
+        assert_eq!(&vertices[3..6], &[1., 0., 0.]);
+    }
+
+    #[test]
+    fn shared_corners_are_one_vertex_unless_normals_differ() {
+        let quad = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3\nf 1 3 4\n";
+        let (vertices, indices) = parse(quad, CoordinateConvention::YUp).unwrap();
+        assert_eq!(vertices.len(), 4 * 6);
+        assert_eq!(indices, vec![0, 1, 2, 0, 2, 3]);
+
+        let creased =
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nvn 1 0 0\nf 1//1 2//1 3//1\nf 1//2 3//2 2//2\n";
+        let (vertices, _) = parse(creased, CoordinateConvention::YUp).unwrap();
+        assert_eq!(vertices.len(), 6 * 6);
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let relative = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\n";
+        assert_eq!(
+            parse(relative, CoordinateConvention::YUp),
+            parse(TRIANGLE, CoordinateConvention::YUp)
+        );
+    }
+
+    #[test]
+    fn other_statements_are_ignored() {
+        let text = format!(
+            "# comment\no cube\ng side\nusemtl red\ns off\n\n{}",
+            TRIANGLE
+        );
+        assert_eq!(
+            parse(&text, CoordinateConvention::YUp),
+            parse(TRIANGLE, CoordinateConvention::YUp)
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        assert_eq!(
+            parse(
+                "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 4\n",
+                CoordinateConvention::YUp
+            ),
+            Err(String::from("line 4: index 4 is out of range"))
+        );
+        assert_eq!(
+            parse("v 0 0\n", CoordinateConvention::YUp),
+            Err(String::from("line 1: expected 3 values"))
+        );
+        assert_eq!(
+            parse("v 0 x 0\n", CoordinateConvention::YUp),
+            Err(String::from("line 1: bad number x"))
+        );
+        assert_eq!(
+            parse("v 0 0 0\nv 1 0 0\nf 1 2\n", CoordinateConvention::YUp),
+            Err(String::from("line 3: a face needs at least 3 vertices"))
+        );
+        assert_eq!(
+            parse("v 0 0 0\nf 1 a 1\n", CoordinateConvention::YUp),
+            Err(String::from("line 2: bad index a"))
+        );
+    }
+
+    #[test]
+    fn z_up_input_is_turned_y_up() {
+        let text = "v 0 0 0\nv 1 0 0\nv 0 0 1\nvn 0 -1 0\nf 1//1 2//1 3//1\n";
+        let (vertices, indices) = parse(text, CoordinateConvention::ZUp).unwrap();
+        assert_eq!(indices, vec![0, 1, 2]);
+        // The top corner is raised along y, and the front, facing -y in Blender, faces +z.
+        assert_eq!(&vertices[12..15], &[0., 1., 0.]);
+        assert_eq!(&vertices[3..6], &[0., 0., 1.]);
+    }
+
+    #[test]
+    fn left_handed_input_is_mirrored_and_rewound() {
+        let text = "v 0 0 1\nv 1 0 1\nv 0 1 1\nvn 1 0 0\nf 1//1 2//1 3//1\n";
+        let (vertices, indices) = parse(text, CoordinateConvention::LeftHanded).unwrap();
+        assert_eq!(&vertices[0..3], &[0., 0., -1.]);
+        assert_eq!(&vertices[6..9], &[1., 0., -1.]);
+        assert_eq!(&vertices[3..6], &[1., 0., 0.]);
+        // Mirroring z would turn the triangle clockwise, so its winding is reversed.
+        assert_eq!(indices, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn text_without_faces_is_rejected() {
+        assert_eq!(
+            parse("v 0 0 0\n", CoordinateConvention::YUp),
+            Err(String::from("no faces found"))
+        );
+        assert_eq!(
+            parse("", CoordinateConvention::YUp),
+            Err(String::from("no faces found"))
+        );
+    }
+}
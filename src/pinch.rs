@@ -0,0 +1,235 @@
+use crate::hands::HandPose;
+use crate::math::{self, Vec3};
+
+// Meters between the thumb and index finger tip joints. A pinch starts closer than the start
+// distance and ends further apart than the end distance, so tracking jitter around a single
+// threshold doesn't toggle it.
+pub const DEFAULT_START_DISTANCE: f32 = 0.015;
+pub const DEFAULT_END_DISTANCE: f32 = 0.03;
+
+const THUMB_TIP: &str = "thumb-tip";
+const INDEX_TIP: &str = "index-finger-tip";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PinchEvent {
+    // Midpoint between the tips when the pinch started.
+    Start(Vec3),
+    // Midpoint between the tips when the pinch ended, or where they were last seen when the
+    // hand stopped being tracked.
+    End(Vec3),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PinchState {
+    pinching: bool,
+    position: Vec3,
+}
+
+impl PinchState {
+    // `tips` are the thumb and index tip positions, None while either isn't tracked.
+    pub fn update(
+        &mut self,
+        tips: Option<(Vec3, Vec3)>,
+        start_distance: f32,
+        end_distance: f32,
+    ) -> Option<PinchEvent> {
+        let (thumb, index) = match tips {
+            Some(tips) => tips,
+            None if self.pinching => {
+                self.pinching = false;
+                return Some(PinchEvent::End(self.position));
+            }
+            None => return None,
+        };
+        let distance = math::length(&math::sub(&thumb, &index));
+        let midpoint = math::scale(&math::add(&thumb, &index), 0.5);
+        if self.pinching {
+            self.position = midpoint;
+            if distance > end_distance {
+                self.pinching = false;
+                return Some(PinchEvent::End(midpoint));
+            }
+        } else if distance < start_distance {
+            self.pinching = true;
+            self.position = midpoint;
+            return Some(PinchEvent::Start(midpoint));
+        }
+        None
+    }
+}
+
+// Pinch state for each tracked hand, keyed by handedness.
+pub struct PinchDetector {
+    pub start_distance: f32,
+    pub end_distance: f32,
+    hands: Vec<(String, PinchState)>,
+}
+
+impl Default for PinchDetector {
+    fn default() -> Self {
+        PinchDetector {
+            start_distance: DEFAULT_START_DISTANCE,
+            end_distance: DEFAULT_END_DISTANCE,
+            hands: Vec::new(),
+        }
+    }
+}
+
+impl PinchDetector {
+    // Forgets every hand without ending its pinch, for when the session goes away.
+    pub fn reset(&mut self) {
+        self.hands.clear();
+    }
+
+    // Returns the handedness and event of each pinch that started or ended this frame. Hands
+    // missing from `hands` end their pinch.
+    pub fn update(&mut self, hands: &[HandPose]) -> Vec<(String, PinchEvent)> {
+        for hand in hands {
+            if !self
+                .hands
+                .iter()
+                .any(|(handedness, _)| *handedness == hand.handedness)
+            {
+                self.hands
+                    .push((hand.handedness.clone(), PinchState::default()));
+            }
+        }
+        let (start_distance, end_distance) = (self.start_distance, self.end_distance);
+        let events = self
+            .hands
+            .iter_mut()
+            .filter_map(|(handedness, state)| {
+                let tips = hands
+                    .iter()
+                    .find(|hand| hand.handedness == *handedness)
+                    .and_then(|hand| {
+                        Some((
+                            hand.joint_position(THUMB_TIP)?,
+                            hand.joint_position(INDEX_TIP)?,
+                        ))
+                    });
+                let event = state.update(tips, start_distance, end_distance)?;
+                Some((handedness.clone(), event))
+            })
+            .collect();
+        self.hands
+            .retain(|(handedness, _)| hands.iter().any(|hand| hand.handedness == *handedness));
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hands::{JointPose, JOINTS};
+
+    const START: f32 = DEFAULT_START_DISTANCE;
+    const END: f32 = DEFAULT_END_DISTANCE;
+
+    // Tips `distance` apart along X, centered on (0, 1, 0).
+    fn tips(distance: f32) -> Option<(Vec3, Vec3)> {
+        Some(([-distance / 2., 1., 0.], [distance / 2., 1., 0.]))
+    }
+
+    fn hand(handedness: &str, distance: f32) -> HandPose {
+        let joints = JOINTS
+            .iter()
+            .map(|joint| {
+                let x = match *joint {
+                    THUMB_TIP => -distance / 2.,
+                    INDEX_TIP => distance / 2.,
+                    _ => return None,
+                };
+                Some(JointPose {
+                    matrix: math::translation(x, 1., 0.).to_vec(),
+                    radius: 0.01,
+                })
+            })
+            .collect();
+        HandPose {
+            handedness: String::from(handedness),
+            joints,
+        }
+    }
+
+    #[test]
+    fn closing_the_tips_starts_a_pinch_at_their_midpoint() {
+        let mut state = PinchState::default();
+        assert_eq!(state.update(tips(0.05), START, END), None);
+        assert_eq!(
+            state.update(tips(0.01), START, END),
+            Some(PinchEvent::Start([0., 1., 0.]))
+        );
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_pinch_between_thresholds() {
+        let mut state = PinchState::default();
+        state.update(tips(0.01), START, END);
+        // Between the start and end distances: still pinching, no new events.
+        assert_eq!(state.update(tips(0.02), START, END), None);
+        assert_eq!(state.update(tips(0.012), START, END), None);
+        assert_eq!(state.update(tips(0.025), START, END), None);
+        assert_eq!(
+            state.update(tips(0.04), START, END),
+            Some(PinchEvent::End([0., 1., 0.]))
+        );
+        // Opening further doesn't end it twice.
+        assert_eq!(state.update(tips(0.08), START, END), None);
+    }
+
+    #[test]
+    fn between_thresholds_does_not_start_a_pinch() {
+        let mut state = PinchState::default();
+        assert_eq!(state.update(tips(0.02), START, END), None);
+    }
+
+    #[test]
+    fn losing_tracking_ends_the_pinch_where_it_was_last_seen() {
+        let mut state = PinchState::default();
+        state.update(Some(([0., 1., -0.005], [0., 1., 0.005])), START, END);
+        assert_eq!(
+            state.update(None, START, END),
+            Some(PinchEvent::End([0., 1., 0.]))
+        );
+        assert_eq!(state.update(None, START, END), None);
+    }
+
+    #[test]
+    fn detector_tracks_each_hand_separately() {
+        let mut detector = PinchDetector::default();
+        let events = detector.update(&[hand("left", 0.01), hand("right", 0.05)]);
+        assert_eq!(
+            events,
+            vec![(String::from("left"), PinchEvent::Start([0., 1., 0.]))]
+        );
+        let events = detector.update(&[hand("left", 0.01), hand("right", 0.01)]);
+        assert_eq!(
+            events,
+            vec![(String::from("right"), PinchEvent::Start([0., 1., 0.]))]
+        );
+    }
+
+    #[test]
+    fn detector_ends_pinches_of_hands_that_disappear() {
+        let mut detector = PinchDetector::default();
+        detector.update(&[hand("right", 0.01)]);
+        let events = detector.update(&[]);
+        assert_eq!(
+            events,
+            vec![(String::from("right"), PinchEvent::End([0., 1., 0.]))]
+        );
+        assert!(detector.update(&[]).is_empty());
+    }
+
+    #[test]
+    fn sensitivity_changes_the_start_distance() {
+        let mut detector = PinchDetector {
+            start_distance: 0.03,
+            end_distance: 0.05,
+            ..PinchDetector::default()
+        };
+        let events = detector.update(&[hand("left", 0.025)]);
+        assert_eq!(events.len(), 1);
+    }
+}
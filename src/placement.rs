@@ -0,0 +1,138 @@
+use crate::math::Mat4;
+
+// Collapses a mesh to a point, hiding it while there is nowhere to show it.
+const HIDDEN: Mat4 = [
+    0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 0., 1.,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlacementState {
+    // The mesh follows the hit test pose, waiting for a select or pinch.
+    Aiming,
+    // Confirmed at this pose, waiting for its anchor to be created.
+    Confirming(Mat4),
+    // Attached to this anchor id.
+    Placed(u32),
+}
+
+// Places one mesh on a real surface: it previews at the hit test pose until confirmed, then
+// stays attached to an anchor created there.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    pub mesh: usize,
+    pub state: PlacementState,
+}
+
+impl Placement {
+    pub fn new(mesh: usize) -> Placement {
+        Placement {
+            mesh,
+            state: PlacementState::Aiming,
+        }
+    }
+
+    // Model matrix for the mesh this frame, given the latest hit and a lookup of anchor poses.
+    // Hidden while aiming at nothing, and once its anchor is no longer tracked.
+    pub fn model<F: FnOnce(u32) -> Option<Mat4>>(&self, hit: Option<Mat4>, anchor_pose: F) -> Mat4 {
+        match self.state {
+            PlacementState::Aiming => hit,
+            PlacementState::Confirming(pose) => Some(pose),
+            PlacementState::Placed(anchor) => anchor_pose(anchor),
+        }
+        .unwrap_or(HIDDEN)
+    }
+
+    // Confirms the current hit while aiming, returning the pose to anchor the mesh at. Ignored
+    // without a hit, and once a placement is under way.
+    pub fn confirm(&mut self, hit: Option<Mat4>) -> Option<Mat4> {
+        match (self.state, hit) {
+            (PlacementState::Aiming, Some(pose)) => {
+                self.state = PlacementState::Confirming(pose);
+                Some(pose)
+            }
+            _ => None,
+        }
+    }
+
+    // Finishes a confirmation with the created anchor's id, or goes back to aiming when the
+    // anchor couldn't be created. Returns false if nothing was being confirmed.
+    pub fn anchored(&mut self, anchor: Option<u32>) -> bool {
+        if let PlacementState::Confirming(_) = self.state {
+            self.state = anchor.map_or(PlacementState::Aiming, PlacementState::Placed);
+            return true;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math;
+
+    fn is_hidden(model: &Mat4) -> bool {
+        math::transform_point(model, &[1., 1., 1.]) == [0., 0., 0.]
+    }
+
+    fn hit() -> Option<Mat4> {
+        Some(math::translation(0., 0., -1.))
+    }
+
+    #[test]
+    fn aiming_follows_the_hit_and_hides_without_one() {
+        let placement = Placement::new(0);
+        assert_eq!(placement.model(hit(), |_| None), hit().unwrap());
+        assert!(is_hidden(&placement.model(None, |_| None)));
+    }
+
+    #[test]
+    fn confirming_without_a_hit_keeps_aiming() {
+        let mut placement = Placement::new(0);
+        assert_eq!(placement.confirm(None), None);
+        assert_eq!(placement.state, PlacementState::Aiming);
+    }
+
+    #[test]
+    fn confirmed_pose_is_held_until_anchored() {
+        let mut placement = Placement::new(0);
+        assert_eq!(placement.confirm(hit()), hit());
+        // Later hits and confirmations don't move it.
+        let elsewhere = Some(math::translation(5., 0., 0.));
+        assert_eq!(placement.confirm(elsewhere), None);
+        assert_eq!(placement.model(elsewhere, |_| None), hit().unwrap());
+
+        assert!(placement.anchored(Some(7)));
+        assert_eq!(placement.state, PlacementState::Placed(7));
+    }
+
+    #[test]
+    fn placed_mesh_follows_its_anchor() {
+        let mut placement = Placement::new(0);
+        placement.confirm(hit());
+        placement.anchored(Some(7));
+        let anchor_pose = math::translation(0., 0.01, -1.);
+        let model = placement.model(hit(), |id| {
+            assert_eq!(id, 7);
+            Some(anchor_pose)
+        });
+        assert_eq!(model, anchor_pose);
+        assert!(is_hidden(&placement.model(hit(), |_| None)));
+        // A placed mesh isn't confirmed again.
+        assert_eq!(placement.confirm(hit()), None);
+    }
+
+    #[test]
+    fn failed_anchor_goes_back_to_aiming() {
+        let mut placement = Placement::new(0);
+        placement.confirm(hit());
+        assert!(placement.anchored(None));
+        assert_eq!(placement.state, PlacementState::Aiming);
+    }
+
+    #[test]
+    fn stray_anchor_results_are_ignored() {
+        let mut placement = Placement::new(0);
+        assert!(!placement.anchored(Some(1)));
+        assert_eq!(placement.state, PlacementState::Aiming);
+    }
+}
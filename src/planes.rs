@@ -0,0 +1,219 @@
+use crate::geometry;
+use crate::math::{self, Mat4, Vec2};
+use crate::memory::{self, GpuMemory};
+use crate::shader::ProgramCache;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // The WebXR Plane Detection Module isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFramePlanes;
+
+    // A set-like XRPlaneSet, undefined unless the "plane-detection" feature is enabled.
+    #[wasm_bindgen(method, getter, js_name = detectedPlanes)]
+    fn detected_planes(this: &XrFramePlanes) -> JsValue;
+
+    type XrPlane;
+
+    #[wasm_bindgen(method, getter, js_name = planeSpace)]
+    fn plane_space(this: &XrPlane) -> XrSpace;
+
+    // DOMPointReadOnly outline on the plane space's x-z plane.
+    #[wasm_bindgen(method, getter)]
+    fn polygon(this: &XrPlane) -> js_sys::Array;
+}
+
+const OCCLUDER_VS: &str = "#version 300 es
+uniform mat4 view;
+uniform mat4 projection;
+in vec3 position;
+void main() {
+    gl_Position = projection * view * vec4(position, 1.0);
+}";
+
+const OCCLUDER_FS: &str = "#version 300 es
+precision highp float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(0);
+}";
+
+// Triangles filling `polygon`, given as [x, z] on a plane's surface, moved by the plane's
+// `pose` into the reference space. Three floats per vertex, three vertices per triangle.
+pub fn occluder_vertices(polygon: &[Vec2], pose: &Mat4) -> Result<Vec<f32>, String> {
+    let indices = geometry::triangulate(polygon)?;
+    Ok(indices
+        .iter()
+        .flat_map(|&index| {
+            let [x, z] = polygon[index as usize];
+            math::transform_point(pose, &[x, 0., z]).to_vec()
+        })
+        .collect())
+}
+
+// Occluder triangles for every plane detected in `frame`. Planes that aren't tracked or whose
+// outline can't be triangulated are left out.
+pub fn detected_occluders(frame: &XrFrame, ref_space: &XrReferenceSpace) -> Vec<f32> {
+    let planes = frame.unchecked_ref::<XrFramePlanes>().detected_planes();
+    let planes = match js_sys::try_iter(&planes) {
+        Ok(Some(planes)) => planes,
+        _ => return Vec::new(),
+    };
+    let mut vertices = Vec::new();
+    for plane in planes.flatten() {
+        let plane: XrPlane = plane.unchecked_into();
+        let pose = if let Some(pose) = frame.get_pose(&plane.plane_space(), ref_space) {
+            math::to_mat4(&pose.transform().matrix())
+        } else {
+            continue;
+        };
+        let polygon: Vec<Vec2> = plane
+            .polygon()
+            .iter()
+            .map(|point| {
+                let point: DomPointReadOnly = point.unchecked_into();
+                [point.x() as f32, point.z() as f32]
+            })
+            .collect();
+        if let Ok(plane_vertices) = occluder_vertices(&polygon, &pose) {
+            vertices.extend(plane_vertices);
+        }
+    }
+    vertices
+}
+
+// Draws detected planes into the depth buffer only, so virtual content behind real surfaces
+// fails the depth test.
+pub struct PlaneOccluder {
+    program: WebGlProgram,
+    vao: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    vertex_count: i32,
+    view_location: Option<WebGlUniformLocation>,
+    projection_location: Option<WebGlUniformLocation>,
+}
+
+impl PlaneOccluder {
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program_cache: &mut ProgramCache,
+    ) -> Result<PlaneOccluder, String> {
+        let program = program_cache.get_or_link(gl, OCCLUDER_VS, OCCLUDER_FS)?;
+
+        let vao = gl
+            .create_vertex_array()
+            .ok_or_else(|| String::from("unable to create vertex array"))?;
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("unable to create buffer"))?;
+        gl.bind_vertex_array(Some(&vao));
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+        let location = gl.get_attrib_location(&program, "position");
+        if location >= 0 {
+            gl.enable_vertex_attrib_array(location as u32);
+            gl.vertex_attrib_pointer_with_i32(
+                location as u32,
+                3,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                0,
+                0,
+            );
+        }
+        gl.bind_vertex_array(None);
+
+        Ok(PlaneOccluder {
+            view_location: gl.get_uniform_location(&program, "view"),
+            projection_location: gl.get_uniform_location(&program, "projection"),
+            program,
+            vao,
+            buffer,
+            vertex_count: 0,
+        })
+    }
+
+    // Planes move and grow as tracking improves, so the triangles are replaced every frame.
+    pub fn sync(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        vertices: &[f32],
+        gpu_memory: &mut GpuMemory,
+    ) {
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&self.buffer));
+        unsafe {
+            let vertices = js_sys::Float32Array::view(vertices);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vertices,
+                WebGl2RenderingContext::DYNAMIC_DRAW,
+            );
+        }
+        gpu_memory.buffer_data(&self.buffer, memory::buffer_bytes(vertices.len(), 4));
+        self.vertex_count = (vertices.len() / 3) as i32;
+    }
+
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        gl.delete_vertex_array(Some(&self.vao));
+        gpu_memory.delete(gl, &self.buffer);
+    }
+
+    pub fn draw(&self, gl: &WebGl2RenderingContext, view: &[f32], projection: &[f32]) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        gl.use_program(Some(&self.program));
+        gl.uniform_matrix4fv_with_f32_array(self.view_location.as_ref(), false, view);
+        gl.uniform_matrix4fv_with_f32_array(self.projection_location.as_ref(), false, projection);
+
+        gl.color_mask(false, false, false, false);
+        // Planes are seen from both sides, e.g. a table from below.
+        gl.disable(WebGl2RenderingContext::CULL_FACE);
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, self.vertex_count);
+        gl.bind_vertex_array(None);
+        gl.enable(WebGl2RenderingContext::CULL_FACE);
+        gl.color_mask(true, true, true, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: [Vec2; 4] = [[-1., -1.], [1., -1.], [1., 1.], [-1., 1.]];
+
+    fn points(vertices: &[f32]) -> Vec<[f32; 3]> {
+        vertices
+            .chunks_exact(3)
+            .map(|point| [point[0], point[1], point[2]])
+            .collect()
+    }
+
+    #[test]
+    fn outline_is_filled_with_triangles() {
+        let vertices = occluder_vertices(&SQUARE, &math::IDENTITY).unwrap();
+        // Two triangles, every vertex on the plane.
+        assert_eq!(vertices.len(), 2 * 3 * 3);
+        assert!(points(&vertices).iter().all(|point| point[1] == 0.));
+    }
+
+    #[test]
+    fn outline_is_moved_by_the_plane_pose() {
+        // A table top 0.75 m up and 2 m ahead.
+        let pose = math::translation(0., 0.75, -2.);
+        let vertices = occluder_vertices(&SQUARE, &pose).unwrap();
+        for [x, y, z] in points(&vertices) {
+            assert_eq!(y, 0.75);
+            assert!(x.abs() == 1. && (z + 2.).abs() == 1.);
+        }
+    }
+
+    #[test]
+    fn degenerate_outlines_are_rejected() {
+        assert!(occluder_vertices(&SQUARE[..2], &math::IDENTITY).is_err());
+        assert!(occluder_vertices(&[[0., 0.], [1., 0.], [2., 0.]], &math::IDENTITY).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // `XRView.requestViewportScale` (dynamic viewport scaling) isn't in web-sys yet.
+    #[wasm_bindgen(extends = XrView)]
+    type XrViewScale;
+
+    #[wasm_bindgen(method, catch, js_name = requestViewportScale)]
+    fn request_viewport_scale(this: &XrViewScale, scale: f64) -> Result<(), JsValue>;
+}
+
+/// Bundled quality settings for the XR layer.
+///
+/// - `Low`: framebuffer scale 0.5, no antialiasing, maximum fixed foveation (1.0).
+/// - `Medium`: framebuffer scale 0.75, antialiasing, medium fixed foveation (0.5).
+/// - `High`: framebuffer scale 1.0, antialiasing, no fixed foveation (0.0).
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityPreset {
+    pub fn lower(self) -> Option<QualityPreset> {
+        match self {
+            QualityPreset::Low => None,
+            QualityPreset::Medium => Some(QualityPreset::Low),
+            QualityPreset::High => Some(QualityPreset::Medium),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct QualitySettings {
+    pub preset: QualityPreset,
+    pub framebuffer_scale: f64,
+    pub antialias: bool,
+    pub fixed_foveation: f32,
+}
+
+impl QualitySettings {
+    pub fn from_preset(preset: QualityPreset) -> QualitySettings {
+        match preset {
+            QualityPreset::Low => QualitySettings {
+                preset,
+                framebuffer_scale: 0.5,
+                antialias: false,
+                fixed_foveation: 1.0,
+            },
+            QualityPreset::Medium => QualitySettings {
+                preset,
+                framebuffer_scale: 0.75,
+                antialias: true,
+                fixed_foveation: 0.5,
+            },
+            QualityPreset::High => QualitySettings {
+                preset,
+                framebuffer_scale: 1.0,
+                antialias: true,
+                fixed_foveation: 0.0,
+            },
+        }
+    }
+
+    // The layer's framebuffer scale, with `framebuffer_scale` taking over from the preset's.
+    pub fn layer_scale(&self, framebuffer_scale: Option<f64>) -> f64 {
+        framebuffer_scale.unwrap_or(self.framebuffer_scale)
+    }
+}
+
+impl Default for QualitySettings {
+    fn default() -> Self {
+        QualitySettings::from_preset(QualityPreset::High)
+    }
+}
+
+/// Buffers requested for the XR layer, kept across quality preset changes.
+///
+/// - `antialias`: overrides the quality preset's antialiasing when set.
+/// - `depth`: a depth buffer, on by default. Without one, depth testing has no effect.
+/// - `stencil`: a stencil buffer, off by default.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerOptions {
+    antialias: Option<bool>,
+    depth: bool,
+    stencil: bool,
+}
+
+#[wasm_bindgen]
+impl LayerOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        antialias: Option<bool>,
+        depth: Option<bool>,
+        stencil: Option<bool>,
+    ) -> LayerOptions {
+        LayerOptions {
+            antialias,
+            depth: depth.unwrap_or(true),
+            stencil: stencil.unwrap_or(false),
+        }
+    }
+}
+
+impl LayerOptions {
+    pub fn depth(&self) -> bool {
+        self.depth
+    }
+
+    pub fn stencil(&self) -> bool {
+        self.stencil
+    }
+}
+
+impl Default for LayerOptions {
+    fn default() -> Self {
+        LayerOptions::new(None, None, None)
+    }
+}
+
+pub fn create_xr_layer(
+    session: &XrSession,
+    gl: &WebGl2RenderingContext,
+    settings: &QualitySettings,
+    options: &LayerOptions,
+    framebuffer_scale: Option<f64>,
+) -> Result<XrWebGlLayer, JsValue> {
+    let mut layer_init = XrWebGlLayerInit::new();
+    layer_init
+        .antialias(options.antialias.unwrap_or(settings.antialias))
+        .depth(options.depth)
+        .stencil(options.stencil)
+        .framebuffer_scale_factor(settings.layer_scale(framebuffer_scale));
+    let layer =
+        XrWebGlLayer::new_with_web_gl2_rendering_context_and_layer_init(session, gl, &layer_init)?;
+    layer.set_fixed_foveation(Some(settings.fixed_foveation));
+    Ok(layer)
+}
+
+pub fn apply_xr_layer(
+    session: &XrSession,
+    gl: &WebGl2RenderingContext,
+    settings: &QualitySettings,
+    options: &LayerOptions,
+    framebuffer_scale: Option<f64>,
+) -> Result<XrWebGlLayer, JsValue> {
+    let xr_gl_layer = create_xr_layer(session, gl, settings, options, framebuffer_scale)?;
+    let mut render_state_init = XrRenderStateInit::new();
+    render_state_init.base_layer(Some(&xr_gl_layer));
+    session.update_render_state_with_state(&render_state_init);
+    Ok(xr_gl_layer)
+}
+
+// Net over-budget frames (over budget counts up, within budget counts down) before stepping down.
+const OVERRUN_FRAMES: u32 = 90;
+// Frames to wait after a step down before judging the new preset.
+const SETTLE_FRAMES: u32 = 180;
+
+// Steps the quality preset down, never below `floor`, when frames keep missing `budget_ms`.
+pub struct AutoQuality {
+    pub budget_ms: f64,
+    pub floor: QualityPreset,
+    overruns: u32,
+    settle: u32,
+    last_time: Option<f64>,
+}
+
+impl AutoQuality {
+    pub fn new(budget_ms: f64, floor: QualityPreset) -> AutoQuality {
+        AutoQuality {
+            budget_ms,
+            floor,
+            overruns: 0,
+            settle: 0,
+            last_time: None,
+        }
+    }
+
+    // Returns the preset to switch to once frames have been over budget for long enough.
+    pub fn update(&mut self, time: f64, current: QualityPreset) -> Option<QualityPreset> {
+        let delta = time - self.last_time.replace(time)?;
+        if self.settle > 0 {
+            self.settle -= 1;
+            return None;
+        }
+        if delta > self.budget_ms {
+            self.overruns += 1;
+        } else {
+            self.overruns = self.overruns.saturating_sub(1);
+        }
+        if self.overruns < OVERRUN_FRAMES {
+            return None;
+        }
+        self.overruns = 0;
+        let lower = current.lower().filter(|lower| *lower >= self.floor)?;
+        self.settle = SETTLE_FRAMES;
+        Some(lower)
+    }
+}
+
+// Eyes rendered at very different resolutions can cause discomfort, so scales stay in
+// [MIN_EYE_SCALE, 1].
+const MIN_EYE_SCALE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EyeScale {
+    pub left: f32,
+    pub right: f32,
+}
+
+impl EyeScale {
+    pub fn new(left: f32, right: f32) -> EyeScale {
+        EyeScale {
+            left: left.clamp(MIN_EYE_SCALE, 1.),
+            right: right.clamp(MIN_EYE_SCALE, 1.),
+        }
+    }
+
+    pub fn for_eye(&self, eye: XrEye) -> f32 {
+        match eye {
+            XrEye::Left => self.left,
+            XrEye::Right => self.right,
+            _ => 1.,
+        }
+    }
+}
+
+impl Default for EyeScale {
+    fn default() -> Self {
+        EyeScale::new(1., 1.)
+    }
+}
+
+// Asks the runtime to render `view` into a viewport scaled by `scale`, which `get_viewport`
+// then returns. Has no effect on runtimes without dynamic viewport scaling.
+pub fn request_viewport_scale(view: &XrView, scale: f32) {
+    if scale >= 1. {
+        return;
+    }
+    // A missing method throws, which is the same as no scaling.
+    let _ = view
+        .unchecked_ref::<XrViewScale>()
+        .request_viewport_scale(scale as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_get_cheaper_going_down() {
+        let high = QualitySettings::from_preset(QualityPreset::High);
+        let medium = QualitySettings::from_preset(QualityPreset::Medium);
+        let low = QualitySettings::from_preset(QualityPreset::Low);
+        assert!(high.framebuffer_scale > medium.framebuffer_scale);
+        assert!(medium.framebuffer_scale > low.framebuffer_scale);
+        assert!(high.fixed_foveation < medium.fixed_foveation);
+        assert!(medium.fixed_foveation < low.fixed_foveation);
+        assert!(!low.antialias);
+        assert_eq!(QualitySettings::default(), high);
+    }
+
+    #[test]
+    fn lower_steps_down_to_low() {
+        assert_eq!(QualityPreset::High.lower(), Some(QualityPreset::Medium));
+        assert_eq!(QualityPreset::Medium.lower(), Some(QualityPreset::Low));
+        assert_eq!(QualityPreset::Low.lower(), None);
+    }
+
+    #[test]
+    fn layer_scale_override_survives_preset_changes() {
+        for preset in [
+            QualityPreset::High,
+            QualityPreset::Medium,
+            QualityPreset::Low,
+        ]
+        .iter()
+        {
+            let settings = QualitySettings::from_preset(*preset);
+            assert_eq!(settings.layer_scale(Some(0.6)), 0.6);
+            assert_eq!(settings.layer_scale(None), settings.framebuffer_scale);
+        }
+    }
+
+    // Feeds frames `frame_ms` apart from time 0, returning the frames at which a step down is
+    // suggested and the preset it suggests.
+    fn run(
+        auto_quality: &mut AutoQuality,
+        current: &mut QualityPreset,
+        frames: u32,
+        frame_ms: f64,
+    ) -> Vec<(u32, QualityPreset)> {
+        let mut changes = Vec::new();
+        for frame in 0..frames {
+            if let Some(lower) = auto_quality.update(frame as f64 * frame_ms, *current) {
+                *current = lower;
+                changes.push((frame, lower));
+            }
+        }
+        changes
+    }
+
+    #[test]
+    fn frames_within_budget_keep_the_preset() {
+        let mut auto_quality = AutoQuality::new(12., QualityPreset::Low);
+        let mut current = QualityPreset::High;
+        assert!(run(&mut auto_quality, &mut current, 1000, 11.).is_empty());
+    }
+
+    #[test]
+    fn sustained_overruns_step_down_once_then_settle() {
+        let mut auto_quality = AutoQuality::new(12., QualityPreset::Low);
+        let mut current = QualityPreset::High;
+        let changes = run(&mut auto_quality, &mut current, 300, 20.);
+        // The first frame only sets the baseline time.
+        assert_eq!(changes, vec![(OVERRUN_FRAMES, QualityPreset::Medium)]);
+    }
+
+    #[test]
+    fn steps_down_again_after_settling() {
+        let mut auto_quality = AutoQuality::new(12., QualityPreset::Low);
+        let mut current = QualityPreset::High;
+        let changes = run(&mut auto_quality, &mut current, 1000, 20.);
+        assert_eq!(
+            changes,
+            vec![
+                (OVERRUN_FRAMES, QualityPreset::Medium),
+                (OVERRUN_FRAMES * 2 + SETTLE_FRAMES, QualityPreset::Low)
+            ]
+        );
+    }
+
+    #[test]
+    fn never_goes_below_the_floor() {
+        let mut auto_quality = AutoQuality::new(12., QualityPreset::Medium);
+        let mut current = QualityPreset::High;
+        run(&mut auto_quality, &mut current, 2000, 20.);
+        assert_eq!(current, QualityPreset::Medium);
+    }
+
+    #[test]
+    fn occasional_spikes_are_absorbed() {
+        let mut auto_quality = AutoQuality::new(12., QualityPreset::Low);
+        let mut time = 0.;
+        // Every other frame over budget nets out to zero overruns.
+        for frame in 0..1000 {
+            time += if frame % 2 == 0 { 20. } else { 5. };
+            assert_eq!(auto_quality.update(time, QualityPreset::High), None);
+        }
+    }
+
+    #[test]
+    fn eye_scales_are_clamped() {
+        assert_eq!(
+            EyeScale::new(0.1, 2.),
+            EyeScale {
+                left: MIN_EYE_SCALE,
+                right: 1.
+            }
+        );
+        assert_eq!(
+            EyeScale::new(0.75, 0.6),
+            EyeScale {
+                left: 0.75,
+                right: 0.6
+            }
+        );
+    }
+
+    #[test]
+    fn eye_scale_picks_the_eye() {
+        let scale = EyeScale::new(0.6, 0.8);
+        assert_eq!(scale.for_eye(XrEye::Left), 0.6);
+        assert_eq!(scale.for_eye(XrEye::Right), 0.8);
+        // Secondary and mono views always render at full resolution.
+        assert_eq!(scale.for_eye(XrEye::None), 1.);
+    }
+
+    #[test]
+    fn default_eye_scale_is_full_resolution() {
+        assert_eq!(EyeScale::default(), EyeScale::new(1., 1.));
+        assert_eq!(EyeScale::default().left, 1.);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::depth::DepthOcclusion;
+use crate::lines::LineRenderer;
+use crate::memory::GpuMemory;
+use crate::mesh::MeshRenderer;
+use crate::multiview::Multiview;
+use crate::planes::PlaneOccluder;
+use crate::stereo_debug::StereoDebug;
+use web_sys::WebGl2RenderingContext;
+
+// The GPU objects `start` creates for a session, kept by the app so `end` can delete them.
+// Programs belong to the program cache, which reuses them across sessions.
+pub struct Renderers {
+    pub mesh: MeshRenderer,
+    pub multiview: Option<Multiview>,
+    pub line: Option<LineRenderer>,
+    // Rebuilt every frame from the input poses, so rays get their own buffer.
+    pub ray: Option<LineRenderer>,
+    pub tap: Option<LineRenderer>,
+    pub plane_occluder: Option<PlaneOccluder>,
+    pub stereo_debug: Option<StereoDebug>,
+    pub depth: DepthOcclusion,
+}
+
+impl Renderers {
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        self.mesh.delete(gl, gpu_memory);
+        if let Some(multiview) = self.multiview {
+            multiview.delete(gl, gpu_memory);
+        }
+        for line in [self.line, self.ray, self.tap].iter_mut() {
+            if let Some(line) = line.take() {
+                line.delete(gl, gpu_memory);
+            }
+        }
+        if let Some(plane_occluder) = self.plane_occluder {
+            plane_occluder.delete(gl, gpu_memory);
+        }
+        if let Some(stereo_debug) = self.stereo_debug {
+            stereo_debug.delete(gl, gpu_memory);
+        }
+        self.depth.delete(gl, gpu_memory);
+    }
+}
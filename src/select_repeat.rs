@@ -0,0 +1,118 @@
+use web_sys::XrInputSource;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RepeatState {
+    Pressed,
+    Held { since: f64 },
+    Repeating { last: f64 },
+}
+
+impl RepeatState {
+    // Advances the state to `now` and returns whether a repeat should fire.
+    pub fn update(&mut self, now: f64, delay_ms: f64, interval_ms: f64) -> bool {
+        match *self {
+            RepeatState::Pressed => {
+                *self = RepeatState::Held { since: now };
+                false
+            }
+            RepeatState::Held { since } if now - since >= delay_ms => {
+                *self = RepeatState::Repeating { last: now };
+                true
+            }
+            RepeatState::Repeating { last } if now - last >= interval_ms => {
+                *self = RepeatState::Repeating { last: now };
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// Re-fires select for held input sources after `delay_ms`, then every `interval_ms`.
+pub struct SelectRepeat {
+    pub delay_ms: f64,
+    pub interval_ms: f64,
+    held: Vec<(XrInputSource, RepeatState)>,
+}
+
+impl SelectRepeat {
+    pub fn new(delay_ms: f64, interval_ms: f64) -> SelectRepeat {
+        SelectRepeat {
+            delay_ms,
+            interval_ms,
+            held: Vec::new(),
+        }
+    }
+
+    pub fn press(&mut self, source: &XrInputSource) {
+        if !self.held.iter().any(|(s, _)| s == source) {
+            self.held.push((source.clone(), RepeatState::Pressed));
+        }
+    }
+
+    pub fn release(&mut self, source: &XrInputSource) {
+        self.held.retain(|(s, _)| s != source);
+    }
+
+    // Forgets every held source, for when the session goes away.
+    pub fn reset(&mut self) {
+        self.held.clear();
+    }
+
+    pub fn update(&mut self, now: f64) -> Vec<XrInputSource> {
+        let (delay_ms, interval_ms) = (self.delay_ms, self.interval_ms);
+        self.held
+            .iter_mut()
+            .filter_map(|(source, state)| {
+                if state.update(now, delay_ms, interval_ms) {
+                    Some(source.clone())
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY_MS: f64 = 500.;
+    const INTERVAL_MS: f64 = 100.;
+
+    // Frame times at which `state`, pressed at 0, fires repeats.
+    fn repeat_times(times: &[f64]) -> Vec<f64> {
+        let mut state = RepeatState::Pressed;
+        times
+            .iter()
+            .copied()
+            .filter(|&time| state.update(time, DELAY_MS, INTERVAL_MS))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_fires_before_the_delay() {
+        assert!(repeat_times(&[0., 100., 250., 499.]).is_empty());
+    }
+
+    #[test]
+    fn fires_after_the_delay_then_every_interval() {
+        let times: Vec<f64> = (0..=8).map(|frame| frame as f64 * 100.).collect();
+        assert_eq!(repeat_times(&times), vec![500., 600., 700., 800.]);
+    }
+
+    #[test]
+    fn slow_frames_fire_at_most_once_per_frame() {
+        assert_eq!(repeat_times(&[0., 1000., 2000.]), vec![1000., 2000.]);
+    }
+
+    #[test]
+    fn delay_counts_from_the_first_frame_after_the_press() {
+        let mut state = RepeatState::Pressed;
+        assert!(!state.update(300., DELAY_MS, INTERVAL_MS));
+        assert_eq!(state, RepeatState::Held { since: 300. });
+        assert!(!state.update(700., DELAY_MS, INTERVAL_MS));
+        assert!(state.update(800., DELAY_MS, INTERVAL_MS));
+    }
+}
@@ -0,0 +1,51 @@
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+use web_sys::*;
+
+#[wasm_bindgen]
+extern "C" {
+    // `navigator.xr` with session modes passed as strings, since web-sys' XrSessionMode has no
+    // immersive-ar variant.
+    pub type XrModes;
+
+    #[wasm_bindgen(method, js_name = isSessionSupported)]
+    pub fn is_session_supported(this: &XrModes, mode: &str) -> js_sys::Promise;
+
+    #[wasm_bindgen(method, js_name = requestSession)]
+    pub fn request_session(this: &XrModes, mode: &str, options: &XrSessionInit) -> js_sys::Promise;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionMode {
+    ImmersiveVr,
+    ImmersiveAr,
+    Inline,
+}
+
+const SESSION_MODES: [SessionMode; 3] = [
+    SessionMode::ImmersiveVr,
+    SessionMode::ImmersiveAr,
+    SessionMode::Inline,
+];
+
+impl SessionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionMode::ImmersiveVr => "immersive-vr",
+            SessionMode::ImmersiveAr => "immersive-ar",
+            SessionMode::Inline => "inline",
+        }
+    }
+}
+
+impl FromStr for SessionMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        SESSION_MODES
+            .iter()
+            .find(|mode| mode.as_str() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown session mode: {}", name))
+    }
+}
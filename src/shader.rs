@@ -0,0 +1,299 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use web_sys::*;
+
+pub fn program_key(vertex_src: &str, fragment_src: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    vertex_src.hash(&mut hasher);
+    fragment_src.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn stage_name(type_: u32) -> &'static str {
+    match type_ {
+        WebGl2RenderingContext::VERTEX_SHADER => "vs",
+        WebGl2RenderingContext::FRAGMENT_SHADER => "fs",
+        _ => "shader",
+    }
+}
+
+// Error for a failed compile. Some drivers return an empty info log, which would leave the
+// caller with an empty message.
+pub fn compile_error(type_: u32, info_log: Option<String>) -> String {
+    let info_log = info_log.filter(|log| !log.trim().is_empty());
+    format!(
+        "{} compile error:{}",
+        stage_name(type_),
+        info_log.unwrap_or_else(|| String::from("no info log"))
+    )
+}
+
+// Compiles `source` and checks COMPILE_STATUS right away, so a broken stage is reported by
+// itself instead of surfacing later as a link failure.
+pub fn compile_shader_checked(
+    gl: &WebGl2RenderingContext,
+    type_: u32,
+    source: &str,
+) -> Result<WebGlShader, String> {
+    let shader = gl
+        .create_shader(type_)
+        .ok_or_else(|| String::from("unable to create shader"))?;
+    gl.shader_source(&shader, source);
+    gl.compile_shader(&shader);
+    if gl
+        .get_shader_parameter(&shader, WebGl2RenderingContext::COMPILE_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(shader)
+    } else {
+        let err = compile_error(type_, gl.get_shader_info_log(&shader));
+        gl.delete_shader(Some(&shader));
+        Err(err)
+    }
+}
+
+pub fn link_program(
+    gl: &WebGl2RenderingContext,
+    vertex_src: &str,
+    fragment_src: &str,
+) -> Result<WebGlProgram, String> {
+    let vs = compile_shader_checked(gl, WebGl2RenderingContext::VERTEX_SHADER, vertex_src)?;
+    let fs = compile_shader_checked(gl, WebGl2RenderingContext::FRAGMENT_SHADER, fragment_src)
+        .inspect_err(|_| gl.delete_shader(Some(&vs)))?;
+
+    let program = gl
+        .create_program()
+        .ok_or_else(|| String::from("unable to create program"))?;
+    gl.attach_shader(&program, &vs);
+    gl.attach_shader(&program, &fs);
+    gl.link_program(&program);
+    // The shaders are only flagged for deletion while attached, and go with the program.
+    gl.delete_shader(Some(&vs));
+    gl.delete_shader(Some(&fs));
+
+    if gl
+        .get_program_parameter(&program, WebGl2RenderingContext::LINK_STATUS)
+        .as_bool()
+        .unwrap_or(false)
+    {
+        Ok(program)
+    } else {
+        let err = format!(
+            "program link error:{}",
+            gl.get_program_info_log(&program).unwrap_or_default()
+        );
+        gl.delete_program(Some(&program));
+        Err(err)
+    }
+}
+
+// Linked programs keyed by the hash of their sources, so identical programs are only compiled once.
+#[derive(Default)]
+pub struct ProgramCache {
+    programs: HashMap<u64, WebGlProgram>,
+}
+
+impl ProgramCache {
+    pub fn get_or_link(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        vertex_src: &str,
+        fragment_src: &str,
+    ) -> Result<WebGlProgram, String> {
+        let key = program_key(vertex_src, fragment_src);
+        if let Some(program) = self.programs.get(&key) {
+            return Ok(program.clone());
+        }
+        let program = link_program(gl, vertex_src, fragment_src)?;
+        self.programs.insert(key, program.clone());
+        Ok(program)
+    }
+
+    pub fn delete_all(&mut self, gl: &WebGl2RenderingContext) {
+        for (_, program) in self.programs.drain() {
+            gl.delete_program(Some(&program));
+        }
+    }
+}
+
+// Float differences below this count as unchanged.
+const UNIFORM_EPSILON: f32 = 1e-6;
+
+// The last value uploaded for each uniform of the main program, so uploads of unchanged values
+// can be skipped. Values are keyed by name only, so they describe a single program: drawing
+// with another program in between is fine, since GL keeps uniforms per program, but a new or
+// relinked main program has to be `invalidate`d.
+#[derive(Default)]
+pub struct UniformCache {
+    values: HashMap<&'static str, Vec<f32>>,
+    pub uploads: u32,
+    pub skipped: u32,
+}
+
+impl UniformCache {
+    // Records `value` for `name`, returning false if it matches the last recorded value.
+    pub fn changed(&mut self, name: &'static str, value: &[f32]) -> bool {
+        let unchanged = match self.values.get(name) {
+            Some(last) => {
+                last.len() == value.len()
+                    && last
+                        .iter()
+                        .zip(value)
+                        .all(|(a, b)| (a - b).abs() <= UNIFORM_EPSILON)
+            }
+            None => false,
+        };
+        if unchanged {
+            self.skipped += 1;
+            return false;
+        }
+        self.values.insert(name, value.to_vec());
+        self.uploads += 1;
+        true
+    }
+
+    pub fn invalidate(&mut self) {
+        self.values.clear();
+    }
+
+    pub fn uniform1i(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        name: &'static str,
+        location: Option<&WebGlUniformLocation>,
+        x: i32,
+    ) {
+        if self.changed(name, &[x as f32]) {
+            gl.uniform1i(location, x);
+        }
+    }
+
+    pub fn uniform2f(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        name: &'static str,
+        location: Option<&WebGlUniformLocation>,
+        value: &[f32; 2],
+    ) {
+        if self.changed(name, value) {
+            gl.uniform2f(location, value[0], value[1]);
+        }
+    }
+
+    pub fn uniform4f(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        name: &'static str,
+        location: Option<&WebGlUniformLocation>,
+        value: &[f32; 4],
+    ) {
+        if self.changed(name, value) {
+            gl.uniform4f(location, value[0], value[1], value[2], value[3]);
+        }
+    }
+
+    pub fn uniform_matrix4fv(
+        &mut self,
+        gl: &WebGl2RenderingContext,
+        name: &'static str,
+        location: Option<&WebGlUniformLocation>,
+        value: &[f32],
+    ) {
+        if self.changed(name, value) {
+            gl.uniform_matrix4fv_with_f32_array(location, false, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_value_is_uploaded() {
+        let mut cache = UniformCache::default();
+        assert!(cache.changed("view", &[1., 2.]));
+        assert_eq!((cache.uploads, cache.skipped), (1, 0));
+    }
+
+    #[test]
+    fn values_within_epsilon_are_skipped() {
+        let mut cache = UniformCache::default();
+        cache.changed("view", &[1., 2.]);
+        assert!(!cache.changed("view", &[1., 2. + UNIFORM_EPSILON / 2.]));
+        assert!(cache.changed("view", &[1., 2. + UNIFORM_EPSILON * 10.]));
+        assert_eq!((cache.uploads, cache.skipped), (2, 1));
+    }
+
+    #[test]
+    fn length_change_is_uploaded() {
+        let mut cache = UniformCache::default();
+        cache.changed("clipPlane", &[0., 1., 0.]);
+        assert!(cache.changed("clipPlane", &[0., 1., 0., 0.]));
+        assert!(cache.changed("clipPlane", &[0., 1., 0.]));
+    }
+
+    #[test]
+    fn names_are_cached_separately() {
+        let mut cache = UniformCache::default();
+        cache.changed("view", &[1.]);
+        assert!(cache.changed("projection", &[1.]));
+    }
+
+    #[test]
+    fn invalidate_forces_the_next_upload() {
+        let mut cache = UniformCache::default();
+        cache.changed("view", &[1.]);
+        cache.invalidate();
+        assert!(cache.changed("view", &[1.]));
+        // Stats are kept.
+        assert_eq!((cache.uploads, cache.skipped), (2, 0));
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    const BROKEN_FS: &str = "#version 300 es
+precision highp float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = undefinedColor;
+}";
+
+    // The driver's own info log for `source`, compiled without the checked wrapper.
+    fn info_log(gl: &WebGl2RenderingContext, type_: u32, source: &str) -> String {
+        let shader = gl.create_shader(type_).unwrap();
+        gl.shader_source(&shader, source);
+        gl.compile_shader(&shader);
+        let info_log = gl.get_shader_info_log(&shader).unwrap_or_default();
+        gl.delete_shader(Some(&shader));
+        info_log
+    }
+
+    #[wasm_bindgen_test]
+    fn broken_fragment_shader_reports_its_info_log() {
+        let gl = crate::create_webgl_context(false, None).unwrap();
+        let expected = info_log(&gl, WebGl2RenderingContext::FRAGMENT_SHADER, BROKEN_FS);
+        assert!(!expected.trim().is_empty());
+
+        let err = compile_shader_checked(&gl, WebGl2RenderingContext::FRAGMENT_SHADER, BROKEN_FS)
+            .unwrap_err();
+        assert!(err.starts_with("fs compile error:"), "{}", err);
+        assert!(err.contains(expected.trim()), "{}", err);
+    }
+
+    #[wasm_bindgen_test]
+    fn link_program_reports_the_broken_stage() {
+        let gl = crate::create_webgl_context(false, None).unwrap();
+        let err = link_program(&gl, crate::VERTEX_SHADER, BROKEN_FS).unwrap_err();
+        assert!(err.starts_with("fs compile error:"), "{}", err);
+        assert!(err.contains("undefinedColor"), "{}", err);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::math::{self, Mat4};
+use crate::memory::{self, GpuMemory};
+use crate::shader::ProgramCache;
+use web_sys::*;
+
+const DEBUG_VS: &str = "#version 300 es
+uniform vec2 offset;
+in vec2 position;
+void main() {
+    gl_Position = vec4(position + offset, 0, 1);
+}";
+
+const DEBUG_FS: &str = "#version 300 es
+precision highp float;
+uniform vec3 color;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(color, 1);
+}";
+
+// Line segments in normalized device coordinates: a border just inside the viewport, then a
+// crosshair that is moved onto the view's optical axis. With asymmetric per-eye frusta the
+// crosshairs sit off the viewport center, mirrored between eyes, so swapped or identical
+// projections stand out.
+const BORDER_VERTEX_COUNT: i32 = 8;
+const CROSSHAIR_VERTEX_COUNT: i32 = 4;
+const VERTICES: [f32; 24] = [
+    -0.98, -0.98, 0.98, -0.98, //
+    0.98, -0.98, 0.98, 0.98, //
+    0.98, 0.98, -0.98, 0.98, //
+    -0.98, 0.98, -0.98, -0.98, //
+    -0.1, 0., 0.1, 0., //
+    0., -0.1, 0., 0.1,
+];
+
+fn eye_color(eye: XrEye) -> [f32; 3] {
+    match eye {
+        XrEye::Left => [1., 0.2, 0.2],
+        XrEye::Right => [0.2, 0.4, 1.],
+        _ => [1., 1., 1.],
+    }
+}
+
+// Where the view's optical axis (straight ahead, -Z in view space) lands in normalized device
+// coordinates. The origin for symmetric frusta, shifted toward the nose for typical HMD eyes.
+fn optical_axis(projection: &Mat4) -> [f32; 2] {
+    let axis = math::transform_point(projection, &[0., 0., -1.]);
+    [axis[0], axis[1]]
+}
+
+// Per-eye viewport border and crosshair, colored by eye (left red, right blue), drawn over
+// the scene without depth testing.
+pub struct StereoDebug {
+    program: WebGlProgram,
+    vao: WebGlVertexArrayObject,
+    buffer: WebGlBuffer,
+    color_location: Option<WebGlUniformLocation>,
+    offset_location: Option<WebGlUniformLocation>,
+}
+
+impl StereoDebug {
+    pub fn new(
+        gl: &WebGl2RenderingContext,
+        program_cache: &mut ProgramCache,
+        gpu_memory: &mut GpuMemory,
+    ) -> Result<StereoDebug, String> {
+        let program = program_cache.get_or_link(gl, DEBUG_VS, DEBUG_FS)?;
+
+        let vao = gl
+            .create_vertex_array()
+            .ok_or_else(|| String::from("unable to create vertex array"))?;
+        let buffer = gl
+            .create_buffer()
+            .ok_or_else(|| String::from("unable to create buffer"))?;
+        gl.bind_vertex_array(Some(&vao));
+        gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
+        unsafe {
+            let vertices = js_sys::Float32Array::view(&VERTICES);
+            gl.buffer_data_with_array_buffer_view(
+                WebGl2RenderingContext::ARRAY_BUFFER,
+                &vertices,
+                WebGl2RenderingContext::STATIC_DRAW,
+            );
+        }
+        gpu_memory.buffer_data(&buffer, memory::buffer_bytes(VERTICES.len(), 4));
+        let location = gl.get_attrib_location(&program, "position");
+        if location >= 0 {
+            gl.enable_vertex_attrib_array(location as u32);
+            gl.vertex_attrib_pointer_with_i32(
+                location as u32,
+                2,
+                WebGl2RenderingContext::FLOAT,
+                false,
+                0,
+                0,
+            );
+        }
+        gl.bind_vertex_array(None);
+
+        Ok(StereoDebug {
+            color_location: gl.get_uniform_location(&program, "color"),
+            offset_location: gl.get_uniform_location(&program, "offset"),
+            program,
+            vao,
+            buffer,
+        })
+    }
+
+    pub fn delete(self, gl: &WebGl2RenderingContext, gpu_memory: &mut GpuMemory) {
+        gl.delete_vertex_array(Some(&self.vao));
+        gpu_memory.delete(gl, &self.buffer);
+    }
+
+    pub fn draw(&self, gl: &WebGl2RenderingContext, projection: &Mat4, eye: XrEye) {
+        gl.use_program(Some(&self.program));
+        let [r, g, b] = eye_color(eye);
+        gl.uniform3f(self.color_location.as_ref(), r, g, b);
+
+        let depth_test = gl.is_enabled(WebGl2RenderingContext::DEPTH_TEST);
+        gl.disable(WebGl2RenderingContext::DEPTH_TEST);
+        gl.bind_vertex_array(Some(&self.vao));
+        gl.uniform2f(self.offset_location.as_ref(), 0., 0.);
+        gl.draw_arrays(WebGl2RenderingContext::LINES, 0, BORDER_VERTEX_COUNT);
+        let [x, y] = optical_axis(projection);
+        gl.uniform2f(self.offset_location.as_ref(), x, y);
+        gl.draw_arrays(
+            WebGl2RenderingContext::LINES,
+            BORDER_VERTEX_COUNT,
+            CROSSHAIR_VERTEX_COUNT,
+        );
+        gl.bind_vertex_array(None);
+        if depth_test {
+            gl.enable(WebGl2RenderingContext::DEPTH_TEST);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frustum with near-plane extents left, right, bottom and top at distance 1, far 100.
+    fn frustum(left: f32, right: f32, bottom: f32, top: f32) -> Mat4 {
+        let (near, far) = (1., 100.);
+        let mut m = [0.; 16];
+        m[0] = 2. * near / (right - left);
+        m[5] = 2. * near / (top - bottom);
+        m[8] = (right + left) / (right - left);
+        m[9] = (top + bottom) / (top - bottom);
+        m[10] = (far + near) / (near - far);
+        m[11] = -1.;
+        m[14] = 2. * far * near / (near - far);
+        m
+    }
+
+    #[test]
+    fn symmetric_frustum_axis_is_centered() {
+        let [x, y] = optical_axis(&frustum(-1., 1., -1., 1.));
+        assert!(x.abs() < 1e-6 && y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn asymmetric_eyes_mirror_each_other() {
+        // The left eye sees further to the left than to the right, and vice versa.
+        let [left_x, left_y] = optical_axis(&frustum(-1.2, 0.8, -1., 1.));
+        let [right_x, right_y] = optical_axis(&frustum(-0.8, 1.2, -1., 1.));
+        assert!((left_x - 0.2).abs() < 1e-6);
+        assert!((right_x + 0.2).abs() < 1e-6);
+        assert!(left_y.abs() < 1e-6 && right_y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn eyes_have_distinct_colors() {
+        assert_ne!(eye_color(XrEye::Left), eye_color(XrEye::Right));
+    }
+}
@@ -0,0 +1,171 @@
+use crate::lines::Line;
+use crate::math::{self, Mat4};
+
+// How long the preview takes to fade out after the tap is released, in milliseconds.
+pub const FADE_MS: f64 = 300.;
+
+// The reticle sits this far along the tap's target ray, in meters.
+const DISTANCE: f32 = 1.;
+const RAY_WIDTH: f32 = 0.002;
+const RETICLE_SIZE: f32 = 0.03;
+const RETICLE_WIDTH: f32 = 0.004;
+const COLOR: [f32; 3] = [1., 1., 1.];
+
+// Preview of a screen tap: a ray along its target ray and a cross-shaped reticle at its end,
+// shown while the tap is held and shrinking away after release. Only the latest tap is kept,
+// so rapid taps replace each other instead of piling up.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TapFeedback {
+    pub enabled: bool,
+    target_ray: Option<Mat4>,
+    released_at: Option<f64>,
+}
+
+impl TapFeedback {
+    pub fn press(&mut self, target_ray: Mat4) {
+        if !self.enabled {
+            return;
+        }
+        self.target_ray = Some(target_ray);
+        self.released_at = None;
+    }
+
+    // Follows the finger while the tap is held. Ignored after release.
+    pub fn update(&mut self, target_ray: Option<Mat4>) {
+        if let (Some(target_ray), None) = (target_ray, self.released_at) {
+            if self.target_ray.is_some() {
+                self.target_ray = Some(target_ray);
+            }
+        }
+    }
+
+    pub fn release(&mut self, time: f64) {
+        if self.target_ray.is_some() && self.released_at.is_none() {
+            self.released_at = Some(time);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.target_ray = None;
+        self.released_at = None;
+    }
+
+    // 1 while held, falling to 0 over FADE_MS after release, 0 without a tap.
+    pub fn fade(&self, time: f64) -> f32 {
+        match (self.target_ray, self.released_at) {
+            (None, _) => 0.,
+            (Some(_), None) => 1.,
+            (Some(_), Some(released_at)) => {
+                (1. - (time - released_at) / FADE_MS).clamp(0., 1.) as f32
+            }
+        }
+    }
+
+    // Lines to draw at frame `time`. Forgets the tap once it has faded out.
+    pub fn lines(&mut self, time: f64) -> Vec<Line> {
+        let fade = self.fade(time);
+        let target_ray = match self.target_ray {
+            Some(target_ray) if fade > 0. => target_ray,
+            _ => {
+                self.reset();
+                return Vec::new();
+            }
+        };
+        let point = |x: f32, y: f32, z: f32| math::transform_point(&target_ray, &[x, y, z]);
+        let size = RETICLE_SIZE * fade / 2.;
+        vec![
+            Line {
+                start: point(0., 0., 0.),
+                end: point(0., 0., -DISTANCE),
+                width: RAY_WIDTH * fade,
+                color: COLOR,
+            },
+            Line {
+                start: point(-size, 0., -DISTANCE),
+                end: point(size, 0., -DISTANCE),
+                width: RETICLE_WIDTH * fade,
+                color: COLOR,
+            },
+            Line {
+                start: point(0., -size, -DISTANCE),
+                end: point(0., size, -DISTANCE),
+                width: RETICLE_WIDTH * fade,
+                color: COLOR,
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> TapFeedback {
+        TapFeedback {
+            enabled: true,
+            ..TapFeedback::default()
+        }
+    }
+
+    #[test]
+    fn disabled_feedback_ignores_taps() {
+        let mut feedback = TapFeedback::default();
+        feedback.press(math::IDENTITY);
+        assert!(feedback.lines(0.).is_empty());
+    }
+
+    #[test]
+    fn held_tap_draws_a_ray_and_reticle_at_its_end() {
+        let mut feedback = enabled();
+        feedback.press(math::translation(0., 1., 0.));
+        let lines = feedback.lines(1000.);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].start, [0., 1., 0.]);
+        assert_eq!(lines[0].end, [0., 1., -DISTANCE]);
+        // The reticle is centered on the end of the ray.
+        for line in &lines[1..] {
+            let center = math::scale(&math::add(&line.start, &line.end), 0.5);
+            assert_eq!(center, lines[0].end);
+        }
+        // Held taps don't fade, however long they last.
+        assert_eq!(feedback.fade(1e6), 1.);
+    }
+
+    #[test]
+    fn held_tap_follows_the_target_ray() {
+        let mut feedback = enabled();
+        feedback.press(math::IDENTITY);
+        feedback.update(Some(math::translation(1., 0., 0.)));
+        assert_eq!(feedback.lines(0.)[0].start, [1., 0., 0.]);
+        // Updates without a tap don't start one.
+        let mut idle = enabled();
+        idle.update(Some(math::IDENTITY));
+        assert!(idle.lines(0.).is_empty());
+    }
+
+    #[test]
+    fn released_tap_fades_out_and_is_forgotten() {
+        let mut feedback = enabled();
+        feedback.press(math::IDENTITY);
+        feedback.release(100.);
+        assert_eq!(feedback.fade(100.), 1.);
+        assert!((feedback.fade(100. + FADE_MS / 2.) - 0.5).abs() < 1e-6);
+        let width = feedback.lines(100. + FADE_MS / 2.)[0].width;
+        assert!((width - RAY_WIDTH / 2.).abs() < 1e-6);
+        assert!(feedback.lines(100. + FADE_MS).is_empty());
+        assert_eq!(feedback, enabled());
+    }
+
+    #[test]
+    fn rapid_taps_replace_the_previous_preview() {
+        let mut feedback = enabled();
+        for i in 0..10 {
+            feedback.press(math::translation(i as f32, 0., 0.));
+            feedback.release(i as f64 * 10.);
+        }
+        let lines = feedback.lines(95.);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].start, [9., 0., 0.]);
+        assert!(lines[0].width > 0.);
+    }
+}
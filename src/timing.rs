@@ -0,0 +1,41 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::XrFrame;
+
+#[wasm_bindgen]
+extern "C" {
+    // `XRFrame.predictedDisplayTime` isn't in web-sys yet, and is undefined on runtimes that
+    // don't implement it.
+    #[wasm_bindgen(extends = XrFrame)]
+    type XrFrameTiming;
+
+    #[wasm_bindgen(method, getter, js_name = predictedDisplayTime)]
+    fn predicted_display_time(this: &XrFrameTiming) -> Option<f64>;
+}
+
+// All times are DOMHighResTimeStamps in milliseconds, on the same time base as
+// `performance.now()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTiming {
+    // The timestamp passed to the XR animation frame callback.
+    pub time: f64,
+    // When the frame is expected to reach the display, if the runtime reports it.
+    pub predicted_display_time: Option<f64>,
+}
+
+impl FrameTiming {
+    pub fn from_frame(time: f64, frame: &XrFrame) -> FrameTiming {
+        FrameTiming {
+            time,
+            predicted_display_time: frame
+                .unchecked_ref::<XrFrameTiming>()
+                .predicted_display_time(),
+        }
+    }
+
+    // Predicted display time minus callback time, or 0 when there is no prediction.
+    pub fn drift(&self) -> f64 {
+        self.predicted_display_time
+            .map_or(0., |predicted| predicted - self.time)
+    }
+}
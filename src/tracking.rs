@@ -0,0 +1,105 @@
+use web_sys::XrReferenceSpaceType;
+
+// Reference space types from most to least capable. Each needs less of the tracking system
+// than the one before it, ending at `viewer` which is always available.
+const FALLBACK_LADDER: [XrReferenceSpaceType; 4] = [
+    XrReferenceSpaceType::BoundedFloor,
+    XrReferenceSpaceType::LocalFloor,
+    XrReferenceSpaceType::Local,
+    XrReferenceSpaceType::Viewer,
+];
+
+pub fn fallback(current: XrReferenceSpaceType) -> Option<XrReferenceSpaceType> {
+    let index = FALLBACK_LADDER
+        .iter()
+        .position(|space_type| *space_type == current)
+        .unwrap_or(0);
+    FALLBACK_LADDER.get(index + 1).copied()
+}
+
+// Decides when the viewer pose has been missing long enough to give up on the reference space.
+pub struct TrackingLoss {
+    pub threshold_ms: f64,
+    lost_since: Option<f64>,
+    pub pending: bool,
+}
+
+impl TrackingLoss {
+    pub fn new(threshold_ms: f64) -> TrackingLoss {
+        TrackingLoss {
+            threshold_ms,
+            lost_since: None,
+            pending: false,
+        }
+    }
+
+    pub fn tracked(&mut self) {
+        self.lost_since = None;
+    }
+
+    // Returns true once per loss when it has lasted `threshold_ms` and no fallback request is
+    // still in flight.
+    pub fn lost(&mut self, time: f64) -> bool {
+        let lost_since = *self.lost_since.get_or_insert(time);
+        if self.pending || time - lost_since < self.threshold_ms {
+            return false;
+        }
+        self.pending = true;
+        self.lost_since = None;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fallback_walks_down_the_ladder() {
+        assert_eq!(
+            fallback(XrReferenceSpaceType::BoundedFloor),
+            Some(XrReferenceSpaceType::LocalFloor)
+        );
+        assert_eq!(
+            fallback(XrReferenceSpaceType::LocalFloor),
+            Some(XrReferenceSpaceType::Local)
+        );
+        assert_eq!(
+            fallback(XrReferenceSpaceType::Local),
+            Some(XrReferenceSpaceType::Viewer)
+        );
+        assert_eq!(fallback(XrReferenceSpaceType::Viewer), None);
+    }
+
+    #[test]
+    fn unbounded_falls_back_to_local_floor() {
+        assert_eq!(
+            fallback(XrReferenceSpaceType::Unbounded),
+            Some(XrReferenceSpaceType::LocalFloor)
+        );
+    }
+
+    #[test]
+    fn short_losses_are_tolerated() {
+        let mut loss = TrackingLoss::new(1000.);
+        assert!(!loss.lost(0.));
+        assert!(!loss.lost(999.));
+        loss.tracked();
+        // The clock restarts after tracking comes back.
+        assert!(!loss.lost(1500.));
+        assert!(!loss.lost(2400.));
+    }
+
+    #[test]
+    fn long_loss_fires_once_until_the_fallback_settles() {
+        let mut loss = TrackingLoss::new(1000.);
+        loss.lost(0.);
+        assert!(loss.lost(1000.));
+        // The fallback request is still in flight.
+        assert!(!loss.lost(5000.));
+        loss.pending = false;
+        // Another full threshold has to pass before falling back further.
+        assert!(!loss.lost(5500.));
+        assert!(loss.lost(6000.));
+    }
+}
@@ -0,0 +1,100 @@
+use crate::math::{self, Mat4, Vec3};
+
+// Finite-difference velocity of a pose between consecutive frames.
+#[derive(Default)]
+pub struct Velocity {
+    last: Option<(Mat4, f64)>,
+    // Meters per second.
+    pub linear: Vec3,
+    // Rotation axis scaled by the angular speed in radians per second.
+    pub angular: Vec3,
+}
+
+impl Velocity {
+    // `time` is in milliseconds. A `None` pose means tracking was lost: the velocity drops to
+    // zero and the next pose starts a fresh history instead of differencing across the gap.
+    pub fn update(&mut self, pose: Option<&Mat4>, time: f64) {
+        let pose = if let Some(pose) = pose {
+            *pose
+        } else {
+            *self = Velocity::default();
+            return;
+        };
+        if let Some((last, last_time)) = self.last {
+            let dt = ((time - last_time) / 1000.) as f32;
+            if dt > 0. {
+                let delta = math::sub(
+                    &[pose[12], pose[13], pose[14]],
+                    &[last[12], last[13], last[14]],
+                );
+                self.linear = math::scale(&delta, 1. / dt);
+                self.angular = math::scale(&math::rotation_delta(&last, &pose), 1. / dt);
+            }
+        }
+        self.last = Some((pose, time));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: &Vec3, expected: &Vec3) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-3, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    // Rotation by `angle` radians around Y.
+    fn yaw(angle: f32) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        [
+            cos, 0., -sin, 0., 0., 1., 0., 0., sin, 0., cos, 0., 0., 0., 0., 1.,
+        ]
+    }
+
+    #[test]
+    fn first_pose_has_no_velocity() {
+        let mut velocity = Velocity::default();
+        velocity.update(Some(&math::translation(1., 2., 3.)), 0.);
+        assert_close(&velocity.linear, &[0., 0., 0.]);
+        assert_close(&velocity.angular, &[0., 0., 0.]);
+    }
+
+    #[test]
+    fn linear_velocity_is_meters_per_second() {
+        let mut velocity = Velocity::default();
+        velocity.update(Some(&math::translation(0., 0., 0.)), 1000.);
+        velocity.update(Some(&math::translation(0.1, 0., -0.2)), 1100.);
+        assert_close(&velocity.linear, &[1., 0., -2.]);
+    }
+
+    #[test]
+    fn angular_velocity_is_axis_times_radians_per_second() {
+        let mut velocity = Velocity::default();
+        velocity.update(Some(&yaw(0.)), 0.);
+        velocity.update(Some(&yaw(0.05)), 50.);
+        assert_close(&velocity.angular, &[0., 1., 0.]);
+    }
+
+    #[test]
+    fn lost_tracking_resets_the_history() {
+        let mut velocity = Velocity::default();
+        velocity.update(Some(&math::translation(0., 0., 0.)), 0.);
+        velocity.update(Some(&math::translation(1., 0., 0.)), 100.);
+        velocity.update(None, 200.);
+        assert_close(&velocity.linear, &[0., 0., 0.]);
+        // Far from the last tracked pose, but not differenced across the gap.
+        velocity.update(Some(&math::translation(5., 0., 0.)), 300.);
+        assert_close(&velocity.linear, &[0., 0., 0.]);
+    }
+
+    #[test]
+    fn repeated_timestamps_keep_the_last_velocity() {
+        let mut velocity = Velocity::default();
+        velocity.update(Some(&math::translation(0., 0., 0.)), 0.);
+        velocity.update(Some(&math::translation(1., 0., 0.)), 1000.);
+        velocity.update(Some(&math::translation(2., 0., 0.)), 1000.);
+        assert_close(&velocity.linear, &[1., 0., 0.]);
+    }
+}
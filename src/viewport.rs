@@ -0,0 +1,92 @@
+use web_sys::XrViewport;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Viewport {
+    pub fn from_xr(viewport: &XrViewport) -> Viewport {
+        Viewport {
+            x: viewport.x(),
+            y: viewport.y(),
+            width: viewport.width(),
+            height: viewport.height(),
+        }
+    }
+
+    // Shrinks the viewport by `pixels` on every side, leaving at least one pixel.
+    pub fn inset(&self, pixels: i32) -> Viewport {
+        let pixels = pixels
+            .max(0)
+            .min((self.width - 1).max(0) / 2)
+            .min((self.height - 1).max(0) / 2);
+        Viewport {
+            x: self.x + pixels,
+            y: self.y + pixels,
+            width: self.width - pixels * 2,
+            height: self.height - pixels * 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EYE: Viewport = Viewport {
+        x: 1000,
+        y: 0,
+        width: 1000,
+        height: 800,
+    };
+
+    #[test]
+    fn inset_shrinks_every_side() {
+        assert_eq!(
+            EYE.inset(10),
+            Viewport {
+                x: 1010,
+                y: 10,
+                width: 980,
+                height: 780,
+            }
+        );
+    }
+
+    #[test]
+    fn zero_or_negative_inset_keeps_the_viewport() {
+        assert_eq!(EYE.inset(0), EYE);
+        assert_eq!(EYE.inset(-5), EYE);
+    }
+
+    #[test]
+    fn large_inset_leaves_at_least_one_pixel() {
+        let inset = EYE.inset(10_000);
+        // Limited by the shorter side, so the height keeps a pixel or two.
+        assert_eq!(inset.height, 2);
+        assert_eq!(inset.width, 202);
+        assert_eq!((inset.x, inset.y), (1399, 399));
+        let odd = Viewport {
+            x: 0,
+            y: 0,
+            width: 5,
+            height: 5,
+        };
+        assert_eq!(odd.inset(10).width, 1);
+    }
+
+    #[test]
+    fn empty_viewport_is_not_inverted() {
+        let empty = Viewport {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+        };
+        assert_eq!(empty.inset(3), empty);
+    }
+}